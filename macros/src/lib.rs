@@ -3,50 +3,103 @@ use convert_case::{Case, Casing};
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::quote;
-use syn::{parse_macro_input, Ident, LitFloat, LitStr};
-use unit_systems::{UnitSystems, BASE_DIMENSIONS};
+use syn::{parse_macro_input, Expr, Ident, LitFloat, LitStr};
+use unit_systems::{UnitSystems, BASE_DIMENSIONS, METRIC_PREFIXES};
 
 fn str2ident(s: LitStr) -> Ident {
     Ident::new(&s.value(), Span::call_site())
 }
+// dimension exponents are written as floats but stored as exact fractions
+fn exponent(x: f64, span: Span) -> syn::Result<proc_macro2::TokenStream> {
+    let den = (1..=1000i32)
+        .find(|d| (x * *d as f64 - (x * *d as f64).round()).abs() < 1e-9)
+        .filter(|d| (x * *d as f64).abs() <= i32::MAX as f64)
+        .ok_or_else(|| {
+            syn::Error::new(
+                span,
+                format!("dimension exponent {} is not a simple fraction", x),
+            )
+        })?;
+    let num = (x * den as f64).round() as i32;
+    Ok(quote! { Rational::new(#num, #den) })
+}
 #[proc_macro]
 pub fn dimensions(input: TokenStream) -> TokenStream {
     let uss = parse_macro_input!(input as UnitSystems);
     let mut mod_output = quote! {};
     let mut map_output = quote! {};
-    for us in uss.0 {
+    let mut derived_output = quote! {};
+    for us in &uss.0 {
         let dim_ident = str2ident(us.dimension.clone());
+        let dimension = &us.dimension;
         let dim_enum = Ident::new(
             &us.dimension.value().to_case(Case::UpperCamel),
             dim_ident.span(),
         );
-        let dim_l = us.dim_l;
-        let dim_m = us.dim_m;
-        let dim_t = us.dim_t;
+        let dim_fields: Vec<Ident> = BASE_DIMENSIONS
+            .iter()
+            .map(|d| Ident::new(d, Span::call_site()))
+            .collect();
+        let dim_exps: syn::Result<Vec<proc_macro2::TokenStream>> = (0..BASE_DIMENSIONS.len())
+            .map(|i| match us.dims.get(i) {
+                Some(x) => exponent(*x, dimension.span()),
+                None => Ok(quote! { Rational::ZERO }),
+            })
+            .collect();
+        let dim_exps = match dim_exps {
+            Ok(exps) => exps,
+            Err(e) => return e.to_compile_error().into(),
+        };
         let names_ident: Vec<Ident> = us.units.iter().map(|u| str2ident(u.name.clone())).collect();
         let names: Vec<LitStr> = us.units.iter().map(|u| u.name.clone()).collect();
         let symbols: Vec<LitStr> = us.units.iter().map(|u| u.symbol.clone()).collect();
         let abbrevs: Vec<LitStr> = us.units.iter().map(|u| u.abbrev.clone()).collect();
 
-        for n in names_ident.clone() {
-            map_output = quote! {
-                #map_output
-                m.insert(#dim_ident::#dim_enum::#n.name(), #dim_enum::#n.quantity());
-                m.insert(#dim_ident::#dim_enum::#n.abbrev(), #dim_enum::#n.quantity());
-                m.insert(#dim_ident::#dim_enum::#n.symbol(), #dim_enum::#n.quantity());
+        for (n, unit) in names_ident.iter().zip(&us.units) {
+            for key in &unit.keys {
+                let key = if *key == unit.name.value() {
+                    quote! { #dim_ident::#dim_enum::#n.name() }
+                } else if *key == unit.abbrev.value() {
+                    quote! { #dim_ident::#dim_enum::#n.abbrev() }
+                } else {
+                    quote! { #dim_ident::#dim_enum::#n.symbol() }
+                };
+                map_output = quote! {
+                    #map_output
+                    m.insert(#key, #dim_enum::#n.quantity());
+                }
             }
         }
-        let conversion_factors: Vec<LitFloat> = us
+        let conversion_factors: Vec<Expr> = us
             .units
             .iter()
             .map(|u| u.conversion_factor.clone())
             .collect();
+        let offsets: Vec<LitFloat> = us
+            .units
+            .iter()
+            .map(|u| {
+                u.offset
+                    .clone()
+                    .unwrap_or_else(|| LitFloat::new("0.", u.name.span()))
+            })
+            .collect();
 
-        let q_units = if BASE_DIMENSIONS.contains(&dim_ident.to_string().as_str()) {
+        let is_base = BASE_DIMENSIONS.contains(&dim_ident.to_string().as_str());
+        if !is_base {
+            let first = &names_ident[0];
+            derived_output = quote! {
+                #derived_output
+                DerivedUnit::new(&#dim_ident::#dim_enum::#first, #dimension),
+            };
+        }
+        let q_units = if is_base {
             quote! {
                 fn quantity(&self) -> Quantity {
                     Quantity {
-                        value: self.conversion_factor(),
+                        // the size is kept in `units`, so that building UNITS_LOOKUP does
+                        // not evaluate factors such as exchange rates
+                        value: 1.,
                         dimensions: self.dimensions(),
                         units: Units {
                             #dim_ident: *self,
@@ -61,7 +114,10 @@ pub fn dimensions(input: TokenStream) -> TokenStream {
                     Quantity {
                         value: self.conversion_factor(),
                         dimensions: self.dimensions(),
-                        units: Default::default(),
+                        units: Units {
+                            derived: Some(DerivedUnit::new(self, #dimension)),
+                            ..Default::default()
+                        },
                     }
                 }
             }
@@ -83,9 +139,7 @@ pub fn dimensions(input: TokenStream) -> TokenStream {
             impl Unit for #dim_enum {
                 fn dimensions(&self) -> Dimensions {
                     Dimensions {
-                        length: #dim_l,
-                        mass: #dim_m,
-                        time: #dim_t,
+                        #(#dim_fields: #dim_exps),*
                     }
                 }
                 fn abbrev(&self) -> &'static str {
@@ -108,6 +162,11 @@ pub fn dimensions(input: TokenStream) -> TokenStream {
                         #(#names_ident => #conversion_factors),*
                     }
                 }
+                fn offset(&self) -> QuantityFloat {
+                    match self {
+                        #(#names_ident => #offsets),*
+                    }
+                }
 
                 #q_units
             }
@@ -115,17 +174,50 @@ pub fn dimensions(input: TokenStream) -> TokenStream {
         );
     }
 
+    // rebuilt when the constants change
+    let constants = uss
+        .1
+        .map(|file| quote! { const _: &str = include_str!(#file); });
+    let prefixes = METRIC_PREFIXES.iter().map(|(pre, abr, sym, pow)| {
+        let pow = *pow as i32;
+        quote! { (#pre, #abr, #sym, #pow) }
+    });
     quote! {
+        /// name, abbrev, symbol and power of ten of the metric prefixes units are declared with
+        pub const METRIC_PREFIXES: &[(&str, &str, &str, i32)] = &[
+            #(#prefixes),*
+        ];
         lazy_static! {
             pub static ref UNITS_LOOKUP: HashMap<&'static str, Quantity> = {
                 let mut m = HashMap::new();
                 #map_output
                 m
             };
-
+            /// The first unit of each derived system, in the order they are declared
+            pub static ref DERIVED_UNITS: Vec<DerivedUnit> = vec![#derived_output];
         }
         #mod_output
-
+        #constants
     }
     .into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponents() {
+        let span = Span::call_site();
+        assert_eq!(
+            exponent(-1.5, span).unwrap().to_string(),
+            quote! { Rational::new(-3i32, 2i32) }.to_string()
+        );
+        assert_eq!(
+            exponent(std::f64::consts::PI, span)
+                .unwrap_err()
+                .to_string(),
+            "dimension exponent 3.141592653589793 is not a simple fraction"
+        );
+    }
+}
@@ -1,21 +1,44 @@
-use syn::{LitFloat, LitStr};
+use syn::{Expr, LitFloat, LitStr};
 
+mod formula;
 mod parse;
 
+pub use parse::METRIC_PREFIXES;
+
 pub struct Unit {
     pub name: LitStr,
     pub abbrev: LitStr,
     pub symbol: LitStr,
-    pub conversion_factor: LitFloat,
+    // a float literal, or a parenthesized constant expression
+    pub conversion_factor: Expr,
+    // zero point of affine scales, in units of this unit: base = factor * (value + offset)
+    pub offset: Option<LitFloat>,
+    // marked `#[prefer]`, to keep the names it shares with other units and constants
+    pub prefer: bool,
+    // the abbrev of the prefix a unit is generated with, as `k` for kilometer
+    pub prefix: Option<String>,
+    // the names, abbrevs and symbols it is looked up by, without those of preferred units
+    pub keys: Vec<String>,
 }
-pub struct UnitSystems(pub Vec<UnitSystem>);
+// with the file of constants, if any, that the names of the units were checked against
+pub struct UnitSystems(pub Vec<UnitSystem>, pub Option<String>);
 
 pub struct UnitSystem {
     pub dimension: LitStr,
     pub units: Vec<Unit>,
-    pub dim_l: LitFloat,
-    pub dim_m: LitFloat,
-    pub dim_t: LitFloat,
+    // exponents in the order of BASE_DIMENSIONS, trailing zeros may be omitted
+    pub dims: Vec<f64>,
 }
 
-pub const BASE_DIMENSIONS: &[&str] = &["length", "mass", "time"];
+pub const BASE_DIMENSIONS: &[&str] = &[
+    "length",
+    "mass",
+    "time",
+    "temperature",
+    "amount",
+    "luminous_intensity",
+    "angle",
+    "information",
+    "money",
+    "current",
+];
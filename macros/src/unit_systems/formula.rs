@@ -0,0 +1,216 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A product of a number and powers of units, as in `550 ft lbf / s`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formula {
+    pub factor: f64,
+    // unit names with their exponents, in the order they are written
+    pub units: Vec<(String, f64)>,
+}
+
+impl Formula {
+    fn number(factor: f64) -> Self {
+        Self {
+            factor,
+            units: vec![],
+        }
+    }
+    fn mul(mut self, other: Self) -> Self {
+        self.factor *= other.factor;
+        self.units.extend(other.units);
+        self
+    }
+    fn pow(self, exp: f64) -> Self {
+        Self {
+            factor: self.factor.powf(exp),
+            units: self.units.into_iter().map(|(u, e)| (u, e * exp)).collect(),
+        }
+    }
+}
+
+// as in GNU units, writing units next to each other binds tighter than `*` and `/`, so that
+// `kg m^2 / A s^3` divides by both `A` and `s^3`
+pub fn parse(formula: &str) -> Result<Formula, String> {
+    let mut chars = formula.chars().peekable();
+    let f = product(&mut chars)?;
+    skip_spaces(&mut chars);
+    match chars.next() {
+        None => Ok(f),
+        Some(c) => Err(format!("unexpected `{}` in formula `{}`", c, formula)),
+    }
+}
+
+fn skip_spaces(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn product(chars: &mut Peekable<Chars>) -> Result<Formula, String> {
+    let mut f = group(chars)?;
+    loop {
+        skip_spaces(chars);
+        match chars.peek() {
+            Some('*') => {
+                chars.next();
+                f = f.mul(group(chars)?);
+            }
+            Some('/') => {
+                chars.next();
+                f = f.mul(group(chars)?.pow(-1.));
+            }
+            _ => return Ok(f),
+        }
+    }
+}
+
+fn group(chars: &mut Peekable<Chars>) -> Result<Formula, String> {
+    let mut f = power(chars)?;
+    loop {
+        skip_spaces(chars);
+        match chars.peek() {
+            Some(c) if c.is_alphanumeric() || *c == '(' || *c == '.' || !c.is_ascii() => {
+                f = f.mul(power(chars)?)
+            }
+            _ => return Ok(f),
+        }
+    }
+}
+
+fn power(chars: &mut Peekable<Chars>) -> Result<Formula, String> {
+    let f = atom(chars)?;
+    skip_spaces(chars);
+    if chars.next_if_eq(&'^').is_none() {
+        return Ok(f);
+    }
+    skip_spaces(chars);
+    let exp = if chars.next_if_eq(&'(').is_some() {
+        // a fraction such as `^(1/2)`
+        let num = signed_number(chars)?;
+        skip_spaces(chars);
+        let den = match chars.next_if_eq(&'/') {
+            Some(_) => signed_number(chars)?,
+            None => 1.,
+        };
+        skip_spaces(chars);
+        chars
+            .next_if_eq(&')')
+            .ok_or("expected `)` after the exponent")?;
+        num / den
+    } else {
+        signed_number(chars)?
+    };
+    Ok(f.pow(exp))
+}
+
+fn atom(chars: &mut Peekable<Chars>) -> Result<Formula, String> {
+    skip_spaces(chars);
+    match chars.peek() {
+        Some('(') => {
+            chars.next();
+            let f = product(chars)?;
+            skip_spaces(chars);
+            chars.next_if_eq(&')').ok_or("expected `)`")?;
+            Ok(f)
+        }
+        Some(c) if c.is_ascii_digit() || *c == '.' => Ok(Formula::number(number(chars)?)),
+        Some(_) => {
+            let mut name = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || !c.is_ascii())
+            {
+                name.push(c);
+            }
+            if name.is_empty() {
+                return Err("expected a unit or a number".to_string());
+            }
+            Ok(Formula {
+                factor: 1.,
+                units: vec![(name, 1.)],
+            })
+        }
+        None => Err("unexpected end of formula".to_string()),
+    }
+}
+
+fn signed_number(chars: &mut Peekable<Chars>) -> Result<f64, String> {
+    skip_spaces(chars);
+    let sign = if chars.next_if_eq(&'-').is_some() {
+        -1.
+    } else {
+        1.
+    };
+    Ok(sign * number(chars)?)
+}
+
+fn number(chars: &mut Peekable<Chars>) -> Result<f64, String> {
+    let mut n = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+        n.push(c);
+    }
+    // an exponent, as long as it is not the start of a unit name such as `erg`
+    let mut ahead = chars.clone();
+    if ahead.next_if_eq(&'e').is_some() {
+        ahead.next_if_eq(&'-');
+        if ahead.peek().is_some_and(char::is_ascii_digit) {
+            n.push(chars.next().unwrap());
+            if let Some(minus) = chars.next_if_eq(&'-') {
+                n.push(minus);
+            }
+            while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                n.push(c);
+            }
+        }
+    }
+    n.parse().map_err(|_| format!("invalid number `{}`", n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn units(f: &Formula) -> Vec<(&str, f64)> {
+        f.units.iter().map(|(u, e)| (u.as_str(), *e)).collect()
+    }
+
+    #[test]
+    fn numbers() {
+        let f = parse("1e-7 J").unwrap();
+        assert_eq!(f.factor, 1e-7);
+        assert_eq!(units(&f), [("J", 1.)]);
+        // `e` starts a unit name unless a digit follows
+        let f = parse("2erg").unwrap();
+        assert_eq!(f.factor, 2.);
+        assert_eq!(units(&f), [("erg", 1.)]);
+        assert_eq!(parse("1.5e3").unwrap(), Formula::number(1500.));
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(units(&parse("m^(1/2)").unwrap()), [("m", 0.5)]);
+        assert_eq!(units(&parse("s^-2").unwrap()), [("s", -2.)]);
+        assert_eq!(
+            units(&parse("(m s)^(-1/3)").unwrap()),
+            [("m", -1. / 3.), ("s", -1. / 3.)]
+        );
+        assert_eq!(parse("(2 m)^2").unwrap().factor, 4.);
+    }
+
+    #[test]
+    fn juxtaposition() {
+        let f = parse("kg m^2 / A s^3").unwrap();
+        assert_eq!(units(&f), [("kg", 1.), ("m", 2.), ("A", -1.), ("s", -3.)]);
+        let f = parse("lbf / in^2 * in").unwrap();
+        assert_eq!(units(&f), [("lbf", 1.), ("in", -2.), ("in", 1.)]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("m)").unwrap_err(), "unexpected `)` in formula `m)`");
+        assert_eq!(
+            parse("m^(1/2").unwrap_err(),
+            "expected `)` after the exponent"
+        );
+        assert_eq!(parse("(m").unwrap_err(), "expected `)`");
+        assert_eq!(parse("m /").unwrap_err(), "unexpected end of formula");
+        assert_eq!(parse("m^x").unwrap_err(), "invalid number ``");
+    }
+}
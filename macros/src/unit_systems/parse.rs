@@ -1,8 +1,16 @@
-use super::{Unit, UnitSystem, UnitSystems};
+use super::{formula, Unit, UnitSystem, UnitSystems, BASE_DIMENSIONS};
+use convert_case::{Case, Casing};
+use proc_macro2::Span;
+use proc_macro2::TokenTree;
+use std::collections::BTreeMap;
 use syn::parse::{Parse, ParseStream, Result};
-use syn::{LitFloat, LitStr, Token};
+use syn::punctuated::Punctuated;
+use syn::{
+    bracketed, parse_quote, token, Attribute, Error, Expr, ExprParen, Ident, LitFloat, LitStr,
+    Token,
+};
 // name, abbrev, symbol, 10^x
-const METRIC_PREFIXES: &[(&str, &str, &str, f64)] = &[
+pub const METRIC_PREFIXES: &[(&str, &str, &str, f64)] = &[
     ("yotta", "Y", "Y", 24.),
     ("zetta", "Z", "Z", 21.),
     ("exa", "E", "E", 18.),
@@ -24,58 +32,594 @@ const METRIC_PREFIXES: &[(&str, &str, &str, f64)] = &[
     ("zepto", "z", "z", -21.),
     ("yocto", "y", "y", -24.),
 ];
+// name, abbrev, symbol, 2^x
+const BINARY_PREFIXES: &[(&str, &str, &str, f64)] = &[
+    ("yobi", "Yi", "Yi", 80.),
+    ("zebi", "Zi", "Zi", 70.),
+    ("exbi", "Ei", "Ei", 60.),
+    ("pebi", "Pi", "Pi", 50.),
+    ("tebi", "Ti", "Ti", 40.),
+    ("gibi", "Gi", "Gi", 30.),
+    ("mebi", "Mi", "Mi", 20.),
+    ("kibi", "Ki", "Ki", 10.),
+];
+
+type Prefix = (&'static str, &'static str, &'static str, String);
+
+// name, abbrev, symbol and scale of each prefix in a table
+fn prefixes(table: &Ident) -> Result<Vec<Prefix>> {
+    let metric = |(pre, abr, sym, pow): &(&'static str, &'static str, &'static str, f64)| {
+        (*pre, *abr, *sym, format!("1e{}", pow))
+    };
+    match table.to_string().as_str() {
+        "metric" => Ok(METRIC_PREFIXES.iter().map(metric).collect()),
+        "multiples" => Ok(METRIC_PREFIXES
+            .iter()
+            .filter(|(_, _, _, pow)| *pow > 0.)
+            .map(metric)
+            .collect()),
+        "binary" => Ok(BINARY_PREFIXES
+            .iter()
+            .map(|(pre, abr, sym, pow)| (*pre, *abr, *sym, format!("{}.", 2u128.pow(*pow as u32))))
+            .collect()),
+        _ => Err(Error::new(
+            table.span(),
+            "unknown prefix table, expected `metric`, `multiples` or `binary`",
+        )),
+    }
+}
+
+// a unit declared so far, which formulas can refer to by name, abbrev or symbol
+struct Known {
+    names: [String; 3],
+    dims: Vec<f64>,
+    // expression of its size in SI units
+    si_factor: Expr,
+    has_offset: bool,
+    prefer: bool,
+}
+
+struct Resolved {
+    dims: Vec<f64>,
+    si_factor: Expr,
+}
+
+fn resolve(formula: &LitStr, known: &[Known]) -> Result<Resolved> {
+    let f = formula::parse(&formula.value()).map_err(|e| Error::new(formula.span(), e))?;
+    let mut dims = vec![0.; BASE_DIMENSIONS.len()];
+    let factor = f.factor;
+    let mut si_factor: Expr = parse_quote! { #factor };
+    for (name, exp) in f.units {
+        // a name shared by units means the preferred one, as in UNITS_LOOKUP
+        let unit = known
+            .iter()
+            .filter(|k| k.names.contains(&name))
+            .max_by_key(|k| k.prefer)
+            .ok_or_else(|| {
+                Error::new(
+                    formula.span(),
+                    format!(
+                        "unknown unit `{}`, formulas can only use units declared before",
+                        name
+                    ),
+                )
+            })?;
+        if unit.has_offset {
+            return Err(Error::new(
+                formula.span(),
+                format!(
+                    "`{}` has a shifted zero point and cannot be used in a formula",
+                    name
+                ),
+            ));
+        }
+        for (d, e) in dims.iter_mut().zip(&unit.dims) {
+            *d += e * exp;
+        }
+        let size = &unit.si_factor;
+        si_factor = if exp == 1. {
+            parse_quote! { #si_factor * #size }
+        } else if exp.fract() == 0. {
+            let exp = exp as i32;
+            parse_quote! { #si_factor * (#size).powi(#exp) }
+        } else {
+            parse_quote! { #si_factor * (#size).powf(#exp) }
+        };
+    }
+    Ok(Resolved { dims, si_factor })
+}
+
+fn same_dims(a: &[f64], b: &[f64]) -> bool {
+    (0..BASE_DIMENSIONS.len())
+        .all(|i| (a.get(i).unwrap_or(&0.) - b.get(i).unwrap_or(&0.)).abs() < 1e-9)
+}
+
+// e.g. `length^2 mass time^-2`
+fn describe(dims: &[f64]) -> String {
+    let factors: Vec<String> = BASE_DIMENSIONS
+        .iter()
+        .zip(dims)
+        .filter(|(_, e)| **e != 0.)
+        .map(|(d, e)| match e {
+            e if *e == 1. => d.to_string(),
+            e => format!("{}^{}", d, e),
+        })
+        .collect();
+    match factors.is_empty() {
+        true => "dimensionless".to_string(),
+        false => factors.join(" "),
+    }
+}
+
+// `#[prefer]` before a unit, which then keeps the names it shares with other units, and
+// `#[no_prefix(h, da)]`, which leaves out the units spelled with these prefixes
+fn options(attrs: &[Attribute]) -> Result<(bool, Vec<Ident>)> {
+    let mut prefer = false;
+    let mut no_prefix = vec![];
+    for attr in attrs {
+        if attr.path.is_ident("prefer") && attr.tokens.is_empty() {
+            prefer = true;
+        } else if attr.path.is_ident("no_prefix") {
+            no_prefix
+                .extend(attr.parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated)?);
+        } else {
+            return Err(Error::new_spanned(
+                attr,
+                "expected `#[prefer]` or `#[no_prefix(..)]`",
+            ));
+        }
+    }
+    Ok((prefer, no_prefix))
+}
+
+// `#![constants = "src/core/constant.rs"]`, a file relative to the crate with a `constants!`
+// invocation, whose names and symbols map to the name of their constant
+fn constants(attrs: &[Attribute]) -> Result<Option<(String, BTreeMap<String, String>)>> {
+    let attr = match attrs {
+        [] => return Ok(None),
+        [attr] if attr.path.is_ident("constants") => attr,
+        [.., attr] => {
+            return Err(Error::new_spanned(
+                attr,
+                "expected `#![constants = \"file\"]`",
+            ))
+        }
+    };
+    let file: LitStr = match attr.parse_meta()? {
+        syn::Meta::NameValue(syn::MetaNameValue {
+            lit: syn::Lit::Str(file),
+            ..
+        }) => file,
+        _ => {
+            return Err(Error::new_spanned(
+                attr,
+                "expected `#![constants = \"file\"]`",
+            ))
+        }
+    };
+    let path = std::path::Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default())
+        .join(file.value());
+    let source = std::fs::read_to_string(&path).map_err(|e| {
+        Error::new(
+            file.span(),
+            format!("cannot read {}: {}", path.display(), e),
+        )
+    })?;
+    let invocation = syn::parse_file(&source)?
+        .items
+        .into_iter()
+        .find_map(|item| match item {
+            syn::Item::Macro(m) if m.mac.path.is_ident("constants") => Some(m.mac.tokens),
+            _ => None,
+        })
+        .ok_or_else(|| Error::new(file.span(), "no `constants!` invocation in the file"))?;
+    // `"name" "symbol".. = value, exponents;`
+    let mut names = BTreeMap::new();
+    let mut keys: Vec<String> = vec![];
+    let mut in_keys = true;
+    for token in invocation {
+        match token {
+            TokenTree::Literal(lit) if in_keys => match syn::Lit::new(lit) {
+                syn::Lit::Str(key) => keys.push(key.value()),
+                lit => return Err(Error::new(lit.span(), "expected the name of a constant")),
+            },
+            TokenTree::Punct(p) if p.as_char() == '=' => in_keys = false,
+            TokenTree::Punct(p) if p.as_char() == ';' => {
+                for key in &keys {
+                    names.insert(key.clone(), keys[0].clone());
+                }
+                keys.clear();
+                in_keys = true;
+            }
+            _ => {}
+        }
+    }
+    Ok(Some((path.display().to_string(), names)))
+}
+
+// gives each name, abbrev and symbol to the one unit it belongs to, so that no unit in
+// UNITS_LOOKUP is overwritten by another one with the same name or a prefix that spells it,
+// and makes sure a unit spelled like a constant is meant to be read as the unit after a number
+fn assign_keys(
+    unit_systems: &mut [UnitSystem],
+    constants: &BTreeMap<String, String>,
+) -> Result<()> {
+    let mut claims: BTreeMap<String, Vec<(usize, usize)>> = BTreeMap::new();
+    for (s, us) in unit_systems.iter().enumerate() {
+        for (u, unit) in us.units.iter().enumerate() {
+            let mut keys = vec![unit.name.value(), unit.abbrev.value(), unit.symbol.value()];
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                claims.entry(key).or_default().push((s, u));
+            }
+        }
+    }
+    let mut errors: Option<Error> = None;
+    for (key, claimants) in claims {
+        let unit = |(s, u): (usize, usize)| &unit_systems[s].units[u];
+        let owner = match claimants[..] {
+            [only] => Some(only),
+            _ => match claimants
+                .iter()
+                .filter(|c| unit(**c).prefer)
+                .collect::<Vec<_>>()[..]
+            {
+                [preferred] => Some(*preferred),
+                _ => None,
+            },
+        };
+        // a prefixed unit cannot be preferred, only left out
+        let remedy = |u: &Unit| match &u.prefix {
+            Some(prefix) => format!("leave the unit out with `#[no_prefix({})]`", prefix),
+            None => {
+                "mark the unit with `#[prefer]` to read it as the unit after a number".to_string()
+            }
+        };
+        let e = match owner {
+            Some(owner) if !unit(owner).prefer && constants.contains_key(&key) => Error::new(
+                unit(owner).name.span(),
+                format!(
+                    "`{}` names {} ({}) and the constant {}, {}",
+                    key,
+                    unit(owner).name.value(),
+                    unit_systems[owner.0].dimension.value(),
+                    constants[&key],
+                    remedy(unit(owner))
+                ),
+            ),
+            Some((s, u)) => {
+                unit_systems[s].units[u].keys.push(key);
+                continue;
+            }
+            None => {
+                let names: Vec<String> = claimants
+                    .iter()
+                    .map(|c| {
+                        format!(
+                            "{} ({})",
+                            unit(*c).name.value(),
+                            unit_systems[c.0].dimension.value()
+                        )
+                    })
+                    .collect();
+                let prefixes: Vec<String> = claimants
+                    .iter()
+                    .filter_map(|c| unit(*c).prefix.clone())
+                    .collect();
+                let remedy = match prefixes[..] {
+                    [] => String::new(),
+                    _ => format!(
+                        " or leave out the prefixed ones with `#[no_prefix({})]`",
+                        prefixes.join(", ")
+                    ),
+                };
+                Error::new(
+                    unit(claimants[0]).name.span(),
+                    format!(
+                        "`{}` names {}, mark the one it should mean with `#[prefer]`{}",
+                        key,
+                        names.join(" and "),
+                        remedy
+                    ),
+                )
+            }
+        };
+        match &mut errors {
+            Some(errors) => errors.combine(e),
+            None => errors = Some(e),
+        }
+    }
+    errors.map_or(Ok(()), Err)
+}
 
 impl Parse for UnitSystems {
     fn parse(input: ParseStream) -> Result<Self> {
+        let constants = constants(&input.call(Attribute::parse_inner)?)?;
         let mut unit_systems = vec![];
+        let mut known: Vec<Known> = vec![];
         while !input.is_empty() {
-            let dimension = input.parse()?;
-            let dim_l: LitFloat = input.parse()?;
-            let dim_m: LitFloat = input.parse()?;
-            let dim_t: LitFloat = input.parse()?;
+            let dimension: LitStr = input.parse()?;
+            let mut dims: Vec<f64> = vec![];
+            if input.parse::<Token![=]>().is_ok() {
+                // exponents of a formula such as `"energy" = "N m"`
+                dims = resolve(&input.parse()?, &known)?.dims;
+            }
+            while !input.peek(Token![:]) {
+                dims.push(input.parse::<LitFloat>()?.base10_parse()?);
+            }
+            if dims.len() > BASE_DIMENSIONS.len() {
+                return Err(Error::new(
+                    dimension.span(),
+                    format!(
+                        "expected at most {} dimension exponents, found {}",
+                        BASE_DIMENSIONS.len(),
+                        dims.len()
+                    ),
+                ));
+            }
             input.parse::<Token![:]>()?;
-            let mut units = vec![];
+            let dim_ident = Ident::new(&dimension.value(), dimension.span());
+            let dim_enum = Ident::new(
+                &dimension.value().to_case(Case::UpperCamel),
+                dimension.span(),
+            );
+            let is_base = BASE_DIMENSIONS.contains(&dimension.value().as_str());
+            let mut units: Vec<Unit> = vec![];
 
             while input.parse::<Token![,]>().is_err() {
+                let declared = units.len();
+                let (prefer, no_prefix) = options(&input.call(Attribute::parse_outer)?)?;
                 let name: LitStr = input.parse()?;
                 let abbrev: LitStr = input.parse()?;
                 let symbol: LitStr = input.parse()?;
-                let conversion_factor = input.parse()?;
+                let conversion_factor: Expr = if input.parse::<Token![=]>().is_ok() {
+                    // `"psi" "psi" "psi" = "lbf / in^2"`, sized in SI units by the formula
+                    let formula: LitStr = input.parse()?;
+                    let resolved = resolve(&formula, &known)?;
+                    if !same_dims(&resolved.dims, &dims) {
+                        return Err(Error::new(
+                            formula.span(),
+                            format!(
+                                "`{}` is {}, but {} is {}",
+                                formula.value(),
+                                describe(&resolved.dims),
+                                dimension.value(),
+                                describe(&dims)
+                            ),
+                        ));
+                    }
+                    let si_factor = resolved.si_factor;
+                    if is_base {
+                        // base units are sized relative to the one declared with 1.
+                        parse_quote! { #si_factor * super::Units::SI().#dim_ident.conversion_factor() }
+                    } else {
+                        si_factor
+                    }
+                } else if input.peek(token::Paren) {
+                    *input.parse::<ExprParen>()?.expr
+                } else {
+                    Expr::Lit(input.parse()?)
+                };
+                let offset = if input.parse::<Token![+]>().is_ok() {
+                    Some(input.parse()?)
+                } else {
+                    None
+                };
                 units.push(Unit {
                     name: name.clone(),
                     abbrev: abbrev.clone(),
                     symbol: symbol.clone(),
-                    conversion_factor,
+                    conversion_factor: conversion_factor.clone(),
+                    offset,
+                    prefer,
+                    prefix: None,
+                    keys: vec![],
                 });
+                let mut left_out = vec![];
                 if input.parse::<Token![!]>().is_ok() {
-                    // metric flag
-                    for (pre, abr, sym, pow) in METRIC_PREFIXES {
+                    // prefix flag, metric unless the tables are listed as in `![multiples, binary]`
+                    let tables: Vec<Ident> = if input.peek(token::Bracket) {
+                        let content;
+                        bracketed!(content in input);
+                        Punctuated::<Ident, Token![,]>::parse_terminated(&content)?
+                            .into_iter()
+                            .collect()
+                    } else {
+                        vec![Ident::new("metric", Span::call_site())]
+                    };
+                    let prefixes = tables
+                        .iter()
+                        .map(prefixes)
+                        .collect::<Result<Vec<_>>>()?
+                        .concat();
+                    for (pre, abr, sym, scale) in prefixes {
+                        if no_prefix.iter().any(|p| p == abr) {
+                            left_out.push(abr);
+                            continue;
+                        }
                         let name: LitStr =
-                            LitStr::new(&(pre.to_string() + &name.value()), dim_l.span());
+                            LitStr::new(&(pre.to_string() + &name.value()), name.span());
                         let symbol: LitStr =
-                            LitStr::new(&(sym.to_string() + &symbol.value()), dim_l.span());
+                            LitStr::new(&(sym.to_string() + &symbol.value()), name.span());
                         let abbrev: LitStr =
-                            LitStr::new(&(abr.to_string() + &abbrev.value()), dim_l.span());
+                            LitStr::new(&(abr.to_string() + &abbrev.value()), name.span());
 
-                        let conversion_factor =
-                            LitFloat::new(&format! {"1e{}", *pow}, dim_l.span());
+                        let scale = LitFloat::new(&scale, name.span());
+                        let conversion_factor: Expr =
+                            parse_quote! { (#conversion_factor) * #scale };
                         units.push(Unit {
                             name,
                             abbrev,
                             symbol,
                             conversion_factor,
+                            offset: None,
+                            prefer: false,
+                            prefix: Some(abr.to_string()),
+                            keys: vec![],
                         });
                     }
                 }
+                if let Some(p) = no_prefix.iter().find(|p| !left_out.iter().any(|l| *p == l)) {
+                    return Err(Error::new(
+                        p.span(),
+                        format!("`{}` is not a prefix of {}", p, name.value()),
+                    ));
+                }
+                for unit in &units[declared..] {
+                    let variant = Ident::new(&unit.name.value(), unit.name.span());
+                    let si_factor = if is_base {
+                        parse_quote! {
+                            (super::#dim_ident::#dim_enum::#variant.conversion_factor()
+                                / super::Units::SI().#dim_ident.conversion_factor())
+                        }
+                    } else {
+                        parse_quote! { super::#dim_ident::#dim_enum::#variant.conversion_factor() }
+                    };
+                    known.push(Known {
+                        names: [unit.name.value(), unit.abbrev.value(), unit.symbol.value()],
+                        dims: dims.clone(),
+                        si_factor,
+                        has_offset: unit.offset.is_some(),
+                        prefer: unit.prefer,
+                    });
+                }
             }
             unit_systems.push(UnitSystem {
                 dimension,
                 units,
-                dim_l,
-                dim_m,
-                dim_t,
+                dims,
             });
         }
-        Ok(UnitSystems(unit_systems))
+        let (file, names) = constants.unzip();
+        assign_keys(&mut unit_systems, &names.unwrap_or_default())?;
+        Ok(UnitSystems(unit_systems, file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(declarations: &str) -> Result<Vec<Vec<String>>> {
+        let systems: UnitSystems = syn::parse_str(declarations)?;
+        Ok(systems.0[1].units.iter().map(|u| u.keys.clone()).collect())
+    }
+
+    #[test]
+    fn collisions() {
+        let declarations = r#"
+            "conductance" -2. -1. 3. 0. 0. 0. 0. 0. 0. 2. :
+            "siemens" "S" "S" 1.![multiples],
+            "power" 2. 1. -3. :
+            "metric_horsepower" "PS" "PS" 735.49875,
+        "#;
+        let e = keys(declarations).err().unwrap();
+        assert_eq!(
+            e.to_string(),
+            "`PS` names petasiemens (conductance) and metric_horsepower (power), \
+             mark the one it should mean with `#[prefer]` or leave out the prefixed ones \
+             with `#[no_prefix(P)]`"
+        );
+        let preferred =
+            declarations.replace("\"metric_horsepower\"", "#[prefer] \"metric_horsepower\"");
+        assert_eq!(
+            keys(&preferred).unwrap(),
+            [["PS".to_string(), "metric_horsepower".to_string()]]
+        );
+    }
+
+    #[test]
+    fn prefixes() {
+        let declarations = r#"
+            "length" 1. :
+            #[prefer] "inch" "in" "in" 0.0254!,
+            "time" 0. 0. 1. :
+            "minute" "min" "min" 60.,
+        "#;
+        // `#[prefer]` keeps `in` for the inch, but does not give `min` to the milliinch
+        let e = keys(declarations).err().unwrap();
+        assert_eq!(
+            e.to_string(),
+            "`min` names milliinch (length) and minute (time), mark the one it should mean with \
+             `#[prefer]` or leave out the prefixed ones with `#[no_prefix(m)]`"
+        );
+        let left_out = declarations.replace("#[prefer]", "#[prefer] #[no_prefix(m)]");
+        assert_eq!(
+            keys(&left_out).unwrap(),
+            [["min".to_string(), "minute".to_string()]]
+        );
+        let e = keys(&declarations.replace("#[prefer]", "#[no_prefix(x)]"))
+            .err()
+            .unwrap();
+        assert_eq!(e.to_string(), "`x` is not a prefix of inch");
+    }
+
+    #[test]
+    fn constants() {
+        let file = std::env::temp_dir().join(format!("larvae_constants_{}.rs", std::process::id()));
+        let constants = r#"constants! {
+            "planck_constant" "h" = 6.6e-34, length 2;
+            "reduced_planck_constant" "hbar" = 1.1e-34, length 2;
+        }"#;
+        std::fs::write(&file, constants).unwrap();
+        let declarations = format!(
+            r#"
+            #![constants = "{}"]
+            "length" 1. :
+            "meter" "m" "m" 1.,
+            "time" 0. 0. 1. :
+            "hour" "h" "hr" 3600.,
+            "#,
+            file.display()
+        );
+        let e = keys(&declarations).err().unwrap();
+        assert_eq!(
+            e.to_string(),
+            "`h` names hour (time) and the constant planck_constant, \
+             mark the unit with `#[prefer]` to read it as the unit after a number"
+        );
+        let preferred = declarations.replace("\"hour\"", "#[prefer] \"hour\"");
+        assert_eq!(
+            keys(&preferred).unwrap(),
+            [["h".to_string(), "hour".to_string(), "hr".to_string()]]
+        );
+        // a prefixed unit can only be left out
+        let prefixed = format!(
+            "{}\"pressure\" -1. 1. -2. :\n\"bar\" \"bar\" \"bar\" 1e5!,",
+            preferred
+        );
+        let e = keys(&prefixed).err().unwrap();
+        assert_eq!(
+            e.to_string(),
+            "`hbar` names hectobar (pressure) and the constant reduced_planck_constant, \
+             leave the unit out with `#[no_prefix(h)]`"
+        );
+        assert!(keys(&prefixed.replace(
+            "\"bar\" \"bar\" \"bar\"",
+            "#[no_prefix(h)] \"bar\" \"bar\" \"bar\""
+        ))
+        .is_ok());
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn formulas() {
+        let declarations = r#"
+            "length" 1. :
+            "meter" "m" "m" 1.!
+            "inch" "in" "in" 0.0254,
+            "area" = "m^2" :
+            "square_inch" "sqin" "sqin" = "in in"
+            "are" "a" "a" = "100 m",
+        "#;
+        let e = keys(declarations).err().unwrap();
+        assert_eq!(e.to_string(), "`100 m` is length, but area is length^2");
+        let e = keys(&declarations.replace("in in", "in ft")).err().unwrap();
+        assert_eq!(
+            e.to_string(),
+            "unknown unit `ft`, formulas can only use units declared before"
+        );
+        assert!(keys(&declarations.replace("100 m", "100 m^2")).is_ok());
     }
 }
@@ -1,14 +1,56 @@
+use crate::core::system::UNIT_SYSTEMS_LOOKUP;
+use crate::core::{gnu_units, registry};
+use crate::core::{DisplayOptions, Quantity};
 use crate::equation::*;
+use earlgrey::{EarleyForest, EarleyParser};
 use log::debug;
 use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
 
 pub struct Cli;
 
 impl Cli {
     pub fn run() {
         let rl = RefCell::new(rustyline::Editor::<()>::new());
-        let input: Box<dyn Iterator<Item = _>> = if std::env::args().len() > 1 {
-            Box::new((0..1).map(|_| std::env::args().skip(1).collect::<Vec<String>>().join(" ")))
+        let mut options = DisplayOptions::default();
+        let mut system = None;
+        let mut unit_files = vec![registry::units_file()];
+        let mut gnu_files = vec![];
+        let mut args = vec![];
+        let mut argv = std::env::args().skip(1);
+        while let Some(flag) = argv.next() {
+            if !flag.starts_with("--") {
+                args.push(flag);
+                continue;
+            }
+            match flag.as_str() {
+                // keep results in base units instead of e.g. N or J
+                "--base-units" => options.named_units = false,
+                // 12 μm rather than 0.000012 m
+                "--prefixes" => options.prefixes = true,
+                // house units, besides those in the config directory
+                "--units" => match argv.next() {
+                    Some(file) => unit_files.push(file.into()),
+                    None => eprintln!("--units needs a file"),
+                },
+                // a GNU units definitions.units
+                "--gnu-units" => match argv.next() {
+                    Some(file) => gnu_files.push(file),
+                    None => eprintln!("--gnu-units needs a file"),
+                },
+                // results in e.g. CGS base units, unless converted with `->`
+                _ => match flag.strip_prefix("--system=") {
+                    Some(name) => match UNIT_SYSTEMS_LOOKUP.get(name) {
+                        Some(s) => system = Some(*s),
+                        None => eprintln!("unknown unit system {}", name),
+                    },
+                    None => eprintln!("unknown option {}", flag),
+                },
+            }
+        }
+        let input: Box<dyn Iterator<Item = _>> = if !args.is_empty() {
+            Box::new((0..1).map(|_| args.join(" ")))
         } else {
             Box::new(
                 (0..)
@@ -17,24 +59,138 @@ impl Cli {
                     .map(|i| i.unwrap()),
             )
         };
-        let parser = parser();
-        let evaler = semanter();
+        for file in gnu_files {
+            match gnu_units::import(file.as_ref()) {
+                Ok(import) => {
+                    for skipped in &import.skipped {
+                        debug!("{}", skipped);
+                    }
+                    eprintln!(
+                        "{}: {} units and {} prefixes, {} definitions skipped",
+                        file,
+                        import.units,
+                        import.prefixes,
+                        import.skipped.len()
+                    );
+                }
+                Err(e) => eprintln!("{}: {}", file, e),
+            }
+        }
+        let parsers = [parser(), constant_parser()];
+        let evaler = semanter(system);
+        for (i, file) in unit_files.iter().enumerate() {
+            // the default file is optional
+            if i == 0 && !file.exists() {
+                continue;
+            }
+            for e in load_units(&parsers, &evaler, file) {
+                eprintln!("{}", e);
+            }
+        }
         for expr in input {
+            // `unit smoot = 1.7018 m` adds a unit for the rest of the session
+            if let Some(definition) = expr.trim_start().strip_prefix("unit ") {
+                rl.borrow_mut().add_history_entry(&expr);
+                match define(&parsers, &evaler, definition) {
+                    Ok(q) => println!("{} = {}", definition.split('=').next().unwrap().trim(), q),
+                    Err(e) => println!("Definition err: {}", e),
+                }
+                continue;
+            }
             let tokens = tokenizer(expr.chars());
             debug!("tokens: {:?}", tokens.collect::<Vec<String>>());
-            let tokens = tokenizer(expr.chars());
-            match parser.parse(tokens) {
-                Err(e) => println!("Parse err: {:?}", e),
-                Ok(state) => {
+            match catch_errors(|| evaluate(&parsers, &evaler, &expr)) {
+                Ok(Err(e)) => println!("Parse err: {:?}", e),
+                Ok(Ok(v)) => {
                     rl.borrow_mut().add_history_entry(&expr);
-                    let val = evaler.eval(&state);
-                    if let Ok(v) = val {
-                        println!("{}", v);
-                    } else {
-                        println!("{:?}", val);
-                    }
+                    println!("{}", v.display(options))
+                }
+                Err(e) => {
+                    rl.borrow_mut().add_history_entry(&expr);
+                    println!("Eval err: {}", e)
                 }
             }
         }
     }
 }
+
+/// Adds the unit of a `name = expression` definition, returning its size
+fn define(
+    parsers: &[EarleyParser],
+    evaler: &EarleyForest<Quantity>,
+    definition: &str,
+) -> Result<Quantity, String> {
+    let (name, expr) = definition
+        .split_once('=')
+        .ok_or("expected `name = expression`")?;
+    let eval = |expr: &str| catch_errors(|| evaluate(parsers, evaler, expr))?;
+    // a bare unit such as `km/h` stands for one of it
+    let q = eval(expr).or_else(|e| eval(&format!("1 {}", expr)).map_err(|_| e))?;
+    registry::define(name.trim(), &q).map_err(|e| e.to_string())?;
+    Ok(q)
+}
+
+/// Adds the units of a file of definitions, one per line and `#` starting a comment, returning
+/// the errors as `file:line: message`
+fn load_units(
+    parsers: &[EarleyParser],
+    evaler: &EarleyForest<Quantity>,
+    file: &Path,
+) -> Vec<String> {
+    let contents = match std::fs::read_to_string(file) {
+        Ok(contents) => contents,
+        Err(e) => return vec![format!("{}: {}", file.display(), e)],
+    };
+    contents
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let definition = line.split('#').next().unwrap_or_default().trim();
+            if definition.is_empty() {
+                return None;
+            }
+            define(parsers, evaler, definition)
+                .err()
+                .map(|e| format!("{}:{}: {}", file.display(), i + 1, e))
+        })
+        .collect()
+}
+
+/// Runs an evaluation, whose errors surface as panics, returning their message instead
+fn catch_errors<T>(eval: impl FnOnce() -> T) -> Result<T, String> {
+    // only while evaluating, so that other panics are still reported
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|info| debug!("{}", info)));
+    let result = panic::catch_unwind(AssertUnwindSafe(eval));
+    panic::set_hook(hook);
+    result.map_err(|e| {
+        e.downcast_ref::<String>()
+            .cloned()
+            .or_else(|| e.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_else(|| "unknown error".to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_file() {
+        let name = format!("larvae_test_units_file_{}.txt", std::process::id());
+        let file = std::env::temp_dir().join(name);
+        let lines = "# lab units\ncount = 1\nplate = 96 count\n\nfurlong = 200 m\nwell = 3 +\n";
+        std::fs::write(&file, lines).unwrap();
+        let errors = load_units(&[parser(), constant_parser()], &semanter(None), &file);
+        let at = |line| format!("{}:{}: ", file.display(), line);
+        assert_eq!(
+            errors,
+            [
+                at(5) + "`furlong` is already a unit of another size",
+                at(6) + "Parse Error: No Rule completes",
+            ]
+        );
+        assert_eq!(registry::unit("plate").unwrap().value, 96.);
+        std::fs::remove_file(file).unwrap();
+    }
+}
@@ -1,7 +1,24 @@
+pub mod constant;
+pub mod currency;
 pub mod dimension;
+pub mod gnu_units;
+pub mod level;
 pub mod quantity;
+pub mod registry;
+pub mod system;
 pub mod unit;
 
 pub use dimension::*;
 pub use quantity::*;
 pub use unit::*;
+
+use std::path::PathBuf;
+
+/// `larvae` in `$XDG_CONFIG_HOME`, otherwise in `~/.config`
+pub fn config_dir() -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default()
+        .join("larvae")
+}
@@ -0,0 +1,80 @@
+use super::dimension::Dimensions;
+use super::quantity::Quantity;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::f64::consts::{E, PI};
+
+// "name" "symbol".. = value in SI units, exponents of the base dimensions
+macro_rules! constants {
+    ($($name:literal $($symbol:literal)* = $value:expr $(, $dim:ident $exp:literal)*;)+) => {
+        lazy_static! {
+            /// Mathematical and CODATA 2018 physical constants by name and symbol
+            pub static ref CONSTANTS_LOOKUP: HashMap<&'static str, Quantity> = {
+                let mut m = HashMap::new();
+                $(
+                    let q = Quantity {
+                        value: $value,
+                        dimensions: Dimensions {
+                            $($dim: $exp.into(),)*
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    };
+                    m.insert($name, q);
+                    $(m.insert($symbol, q);)*
+                )+
+                m
+            };
+        }
+    };
+}
+
+constants! {
+    // `e` is always Euler's number, the elementary charge is `q_e`
+    "euler" "e" = E;
+    "pi" "π" = PI;
+    "speed_of_light" "c" = 299792458., length 1, time -1;
+    "gravitational_constant" "G" = 6.67430e-11, length 3, mass -1, time -2;
+    "planck_constant" "h" = 6.62607015e-34, length 2, mass 1, time -1;
+    "reduced_planck_constant" "hbar" "ħ" = 1.054571817e-34, length 2, mass 1, time -1;
+    "boltzmann_constant" "k_B" = 1.380649e-23, length 2, mass 1, time -2, temperature -1;
+    "avogadro_constant" "N_A" "NA" = 6.02214076e23, amount -1;
+    "gas_constant" "R" = 8.314462618, length 2, mass 1, time -2, temperature -1, amount -1;
+    "faraday_constant" "F" = 96485.33212, time 1, current 1, amount -1;
+    "elementary_charge" "q_e" = 1.602176634e-19, time 1, current 1;
+    "vacuum_permeability" "mu0" "μ0" = 1.25663706212e-6, length 1, mass 1, time -2, current -2;
+    "vacuum_permittivity" "eps0" "ε0" = 8.8541878128e-12, length -3, mass -1, time 4, current 2;
+    "coulomb_constant" "k_e" = 8.9875517923e9, length 3, mass 1, time -4, current -2;
+    "standard_gravity" "g0" "g_0" = 9.80665, length 1, time -2;
+    "electron_mass" "m_e" = 9.1093837015e-31, mass 1;
+    "proton_mass" "m_p" = 1.67262192369e-27, mass 1;
+    "neutron_mass" "m_n" = 1.67492749804e-27, mass 1;
+    "atomic_mass_constant" "m_u" = 1.66053906660e-27, mass 1;
+    "stefan_boltzmann_constant" "sigma" "σ" = 5.670374419e-8, mass 1, time -3, temperature -4;
+    "fine_structure_constant" "alpha" "α" = 7.2973525693e-3;
+    "rydberg_constant" "R_inf" = 10973731.568160, length -1;
+    "bohr_radius" "a_0" = 5.29177210903e-11, length 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbols_and_names_agree() {
+        assert_eq!(
+            CONSTANTS_LOOKUP["ħ"],
+            CONSTANTS_LOOKUP["reduced_planck_constant"]
+        );
+        let h = CONSTANTS_LOOKUP["h"].value / (2. * PI);
+        assert!((CONSTANTS_LOOKUP["hbar"].value - h).abs() < 1e-42);
+    }
+
+    #[test]
+    fn derived_constants() {
+        let (k, n_a) = (CONSTANTS_LOOKUP["k_B"], CONSTANTS_LOOKUP["N_A"]);
+        let r = k * n_a;
+        assert_eq!(r.dimensions, CONSTANTS_LOOKUP["R"].dimensions);
+        assert!((r.value - CONSTANTS_LOOKUP["R"].value).abs() < 1e-8);
+    }
+}
@@ -0,0 +1,126 @@
+use super::quantity::QuantityFloat;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+lazy_static! {
+    static ref EXCHANGE_RATES: Result<ExchangeRates, String> = ExchangeRates::load();
+}
+
+/// Value of one unit of the currency `code`, in the base currency of the rates file.
+///
+/// Panics if the rates file is missing, malformed or has no rate for `code`.
+pub fn value_of(code: &str) -> QuantityFloat {
+    EXCHANGE_RATES
+        .as_ref()
+        .map_err(String::clone)
+        .and_then(|rates| rates.value_of(code))
+        .unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// `$LARVAE_RATES`, otherwise `rates.toml` in the larvae config directory
+pub fn rates_file() -> PathBuf {
+    if let Some(path) = std::env::var_os("LARVAE_RATES") {
+        return path.into();
+    }
+    super::config_dir().join("rates.toml")
+}
+
+/// Exchange rates as units of each currency per unit of `base`, read from a file like
+///
+/// ```toml
+/// base = "USD"
+///
+/// [rates]
+/// EUR = 0.92
+/// GBP = 0.79
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeRates {
+    base: String,
+    rates: HashMap<String, QuantityFloat>,
+}
+
+impl ExchangeRates {
+    fn load() -> Result<Self, String> {
+        let path = rates_file();
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("cannot read exchange rates {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}:{}", path.display(), e))
+    }
+
+    /// Reads the flat subset of TOML used by rates files, errors are prefixed with the line number
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut base = "USD".to_string();
+        let mut rates = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() || (line.starts_with('[') && line.ends_with(']')) {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| format!("{}: expected `CODE = rate`", i + 1))?;
+            if key == "base" {
+                base = value.trim_matches('"').to_string();
+                continue;
+            }
+            let rate: QuantityFloat = value
+                .parse()
+                .ok()
+                .filter(|r: &QuantityFloat| r.is_finite() && *r > 0.)
+                .ok_or_else(|| format!("{}: invalid rate `{}` for {}", i + 1, value, key))?;
+            rates.insert(key.to_string(), rate);
+        }
+        Ok(Self { base, rates })
+    }
+
+    pub fn value_of(&self, code: &str) -> Result<QuantityFloat, String> {
+        if code == self.base {
+            return Ok(1.);
+        }
+        self.rates.get(code).map(|rate| 1. / rate).ok_or_else(|| {
+            format!(
+                "no exchange rate for {} in {}",
+                code,
+                rates_file().display()
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATES: &str = r#"
+        # refreshed by hand
+        base = "EUR"
+
+        [rates]
+        USD = 1.25
+        GBP = 0.8 # per euro
+    "#;
+
+    #[test]
+    fn parse() {
+        let rates = ExchangeRates::parse(RATES).unwrap();
+        assert_eq!(Ok(1.), rates.value_of("EUR"));
+        assert_eq!(Ok(0.8), rates.value_of("USD"));
+        assert_eq!(Ok(1.25), rates.value_of("GBP"));
+        assert!(rates.value_of("JPY").is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Err("2: expected `CODE = rate`".to_string()),
+            ExchangeRates::parse("USD = 1\nEUR 0.9")
+        );
+        assert_eq!(
+            Err("1: invalid rate `-3` for EUR".to_string()),
+            ExchangeRates::parse("EUR = -3")
+        );
+    }
+}
@@ -1,24 +1,90 @@
-type DimensionFloat = f64;
+mod rational;
+
+pub use rational::Rational;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct DimensionError(pub Dimensions, pub Dimensions);
 
-#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct Dimensions {
-    pub length: DimensionFloat,
-    pub mass: DimensionFloat,
-    pub time: DimensionFloat,
-    //    current: Dim,
-    //    temprature: Dim,
-    //    luminous_intensity: Dim,
-    //    money: Dim,
+    pub length: Rational,
+    pub mass: Rational,
+    pub time: Rational,
+    pub temperature: Rational,
+    pub amount: Rational,
+    pub luminous_intensity: Rational,
+    // pseudo-dimension: tracked and shown, but radians count as dimensionless
+    pub angle: Rational,
+    pub information: Rational,
+    pub money: Rational,
+    pub current: Rational,
 }
 impl Dimensions {
-    pub fn pow(&self, x: DimensionFloat) -> Self {
+    pub fn is_dimensionless(&self) -> bool {
+        *self == Self::default()
+    }
+    pub fn is_temperature(&self) -> bool {
+        *self
+            == Self {
+                temperature: Rational::ONE,
+                ..Default::default()
+            }
+    }
+    /// Number of base dimensions with a nonzero exponent
+    pub fn base_count(&self) -> usize {
+        [
+            self.length,
+            self.mass,
+            self.time,
+            self.temperature,
+            self.amount,
+            self.luminous_intensity,
+            self.angle,
+            self.information,
+            self.money,
+            self.current,
+        ]
+        .iter()
+        .filter(|exp| **exp != Rational::ZERO)
+        .count()
+    }
+    pub fn without_angle(&self) -> Self {
+        Self {
+            angle: Rational::ZERO,
+            ..*self
+        }
+    }
+    /// Equal up to angles, which physics treats as dimensionless (e.g. rad/s and Hz)
+    pub fn is_compatible(&self, r: &Self) -> bool {
+        self.without_angle() == r.without_angle()
+    }
+    /// `None` if an exponent grows out of range
+    pub fn pow(&self, x: Rational) -> Option<Self> {
+        Some(Self {
+            length: self.length.checked_mul(x)?,
+            mass: self.mass.checked_mul(x)?,
+            time: self.time.checked_mul(x)?,
+            temperature: self.temperature.checked_mul(x)?,
+            amount: self.amount.checked_mul(x)?,
+            luminous_intensity: self.luminous_intensity.checked_mul(x)?,
+            angle: self.angle.checked_mul(x)?,
+            information: self.information.checked_mul(x)?,
+            money: self.money.checked_mul(x)?,
+            current: self.current.checked_mul(x)?,
+        })
+    }
+    pub fn inv(&self) -> Self {
         Self {
-            length: self.length * x,
-            mass: self.mass * x,
-            time: self.time * x,
+            length: -self.length,
+            mass: -self.mass,
+            time: -self.time,
+            temperature: -self.temperature,
+            amount: -self.amount,
+            luminous_intensity: -self.luminous_intensity,
+            angle: -self.angle,
+            information: -self.information,
+            money: -self.money,
+            current: -self.current,
         }
     }
     pub fn mul(&self, r: &Self) -> Self {
@@ -26,6 +92,48 @@ impl Dimensions {
             length: self.length + r.length,
             mass: self.mass + r.mass,
             time: self.time + r.time,
+            temperature: self.temperature + r.temperature,
+            amount: self.amount + r.amount,
+            luminous_intensity: self.luminous_intensity + r.luminous_intensity,
+            angle: self.angle + r.angle,
+            information: self.information + r.information,
+            money: self.money + r.money,
+            current: self.current + r.current,
+        }
+    }
+}
+
+impl std::fmt::Display for Dimensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let dims = [
+            ("length", self.length),
+            ("mass", self.mass),
+            ("time", self.time),
+            ("temperature", self.temperature),
+            ("amount", self.amount),
+            ("luminous_intensity", self.luminous_intensity),
+            ("angle", self.angle),
+            ("information", self.information),
+            ("money", self.money),
+            ("current", self.current),
+        ];
+        let named: Vec<String> = dims
+            .iter()
+            .filter(|(_, exp)| *exp != Rational::ZERO)
+            .map(|(name, exp)| {
+                if *exp == Rational::ONE {
+                    name.to_string()
+                } else if exp.is_integer() {
+                    format!("{}^{}", name, exp)
+                } else {
+                    format!("{}^({})", name, exp)
+                }
+            })
+            .collect();
+        if named.is_empty() {
+            write!(f, "dimensionless")
+        } else {
+            write!(f, "{}", named.join("·"))
         }
     }
 }
@@ -37,40 +145,76 @@ mod test {
     #[test]
     pub fn test_pow() {
         let d = Dimensions {
-            length: 1.,
-            mass: 2.,
+            length: 1.into(),
+            mass: 2.into(),
             ..Default::default()
         };
 
         assert_eq!(
-            d.pow(2.),
+            d.pow(2.into()).unwrap(),
             Dimensions {
-                length: 2.,
-                mass: 4.,
+                length: 2.into(),
+                mass: 4.into(),
                 ..Default::default()
             }
         )
     }
 
+    #[test]
+    pub fn test_fractional_pow() {
+        let d = Dimensions {
+            length: 1.into(),
+            ..Default::default()
+        };
+        let root = d.pow(Rational::new(1, 3)).unwrap();
+
+        assert_eq!(
+            root,
+            Dimensions {
+                length: Rational::new(1, 3),
+                ..Default::default()
+            }
+        );
+        assert_eq!(root.pow(3.into()), Some(d));
+        assert_eq!(d.pow(i32::MAX.into()).unwrap().pow(2.into()), None);
+        assert_eq!(root.inv().mul(&root), Dimensions::default());
+        assert_eq!(root.mul(&root).mul(&root), d);
+    }
+
     #[test]
     pub fn test_mul() {
         let d = Dimensions {
-            length: 1.,
-            mass: 3.,
+            length: 1.into(),
+            mass: 3.into(),
             ..Default::default()
         };
 
         assert_eq!(
             d.mul(&Dimensions {
-                length: -1.,
-                mass: 2.,
+                length: (-1).into(),
+                mass: 2.into(),
                 ..Default::default()
             }),
             Dimensions {
-                length: 0.,
-                mass: 5.,
+                length: 0.into(),
+                mass: 5.into(),
                 ..Default::default()
             }
         )
     }
+
+    #[test]
+    pub fn test_angle_compatibility() {
+        let per_second = Dimensions {
+            time: (-1).into(),
+            ..Default::default()
+        };
+        let radians_per_second = Dimensions {
+            angle: 1.into(),
+            ..per_second
+        };
+        assert_ne!(per_second, radians_per_second);
+        assert!(per_second.is_compatible(&radians_per_second));
+        assert!(!per_second.is_compatible(&Dimensions::default()));
+    }
 }
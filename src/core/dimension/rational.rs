@@ -0,0 +1,235 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+// largest denominator considered when recovering a fraction from a float
+const MAX_DENOMINATOR: i32 = 1000;
+
+/// An exact fraction, always kept in lowest terms with a positive denominator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    num: i32,
+    den: i32,
+}
+
+const fn gcd(a: i32, b: i32) -> i32 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        let t = b;
+        b = a % b;
+        a = t;
+    }
+    a
+}
+
+impl Rational {
+    pub const ZERO: Self = Self { num: 0, den: 1 };
+    pub const ONE: Self = Self { num: 1, den: 1 };
+
+    pub const fn new(num: i32, den: i32) -> Self {
+        assert!(den != 0, "Rational with zero denominator");
+        let g = gcd(num, den);
+        let sign = if den < 0 { -1 } else { 1 };
+        Self {
+            num: sign * num / g,
+            den: sign * den / g,
+        }
+    }
+    pub const fn numer(&self) -> i32 {
+        self.num
+    }
+    pub const fn is_integer(&self) -> bool {
+        self.den == 1
+    }
+    pub const fn is_positive(&self) -> bool {
+        self.num > 0
+    }
+    pub const fn is_negative(&self) -> bool {
+        self.num < 0
+    }
+    /// The fraction `num/den` in lowest terms, or `None` if it does not fit in an `i32`
+    fn checked(num: i64, den: i64) -> Option<Self> {
+        if den == 0 {
+            return None;
+        }
+        let (mut a, mut b) = (num.abs(), den.abs());
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        let sign = den.signum();
+        // without i32::MIN, so that negating never overflows
+        let fit = |n: i64| i32::try_from(n).ok().filter(|n| *n != i32::MIN);
+        Some(Self {
+            num: fit(sign * num / a)?,
+            den: fit(sign * den / a)?,
+        })
+    }
+    pub fn checked_add(self, r: Self) -> Option<Self> {
+        let (a, b, c, d) = (self.num as i64, self.den as i64, r.num as i64, r.den as i64);
+        Self::checked(a * d + c * b, b * d)
+    }
+    pub fn checked_mul(self, r: Self) -> Option<Self> {
+        Self::checked(
+            self.num as i64 * r.num as i64,
+            self.den as i64 * r.den as i64,
+        )
+    }
+    pub fn checked_div(self, r: Self) -> Option<Self> {
+        Self::checked(
+            self.num as i64 * r.den as i64,
+            self.den as i64 * r.num as i64,
+        )
+    }
+    pub fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+    /// Recovers the fraction a float was computed from, e.g. `1. / 3.` gives `1/3`.
+    /// Returns `None` if no fraction with a small denominator is close enough.
+    pub fn approximate(x: f64) -> Option<Self> {
+        if !x.is_finite() {
+            return None;
+        }
+        // continued fraction expansion, stopping once the convergent matches x
+        let (mut h0, mut h1) = (0i32, 1i32);
+        let (mut k0, mut k1) = (1i32, 0i32);
+        let mut rest = x;
+        loop {
+            let a = rest.floor();
+            if a.abs() > i16::MAX as f64 {
+                return None;
+            }
+            let a = a as i32;
+            let (h2, k2) = (a * h1 + h0, a * k1 + k0);
+            if k2 > MAX_DENOMINATOR {
+                return None;
+            }
+            if (h2 as f64 / k2 as f64 - x).abs() <= 1e-9 * x.abs().max(1.) {
+                return Some(Self::new(h2, k2));
+            }
+            (h0, h1, k0, k1) = (h1, h2, k1, k2);
+            rest = 1. / (rest - a as f64);
+        }
+    }
+}
+
+impl Default for Rational {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl From<i32> for Rational {
+    fn from(n: i32) -> Self {
+        Self::new(n, 1)
+    }
+}
+
+impl Add for Rational {
+    type Output = Self;
+    fn add(self, r: Self) -> Self {
+        self.checked_add(r).expect("exponent out of range")
+    }
+}
+
+impl Sub for Rational {
+    type Output = Self;
+    fn sub(self, r: Self) -> Self {
+        self + -r
+    }
+}
+
+impl Mul for Rational {
+    type Output = Self;
+    fn mul(self, r: Self) -> Self {
+        self.checked_mul(r).expect("exponent out of range")
+    }
+}
+
+impl Div for Rational {
+    type Output = Self;
+    fn div(self, r: Self) -> Self {
+        self.checked_div(r).expect("exponent out of range")
+    }
+}
+
+impl Neg for Rational {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self {
+            num: -self.num,
+            den: self.den,
+        }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.num as i64 * other.den as i64).cmp(&(other.num as i64 * self.den as i64))
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowest_terms() {
+        assert_eq!(Rational::new(2, 6), Rational::new(1, 3));
+        assert_eq!(Rational::new(3, -6), Rational::new(-1, 2));
+        assert_eq!(Rational::new(0, 5), Rational::ZERO);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let third = Rational::new(1, 3);
+        assert_eq!(third + third + third, Rational::ONE);
+        assert_eq!(third * Rational::from(3), Rational::ONE);
+        assert_eq!(third - Rational::ONE, Rational::new(-2, 3));
+        assert_eq!(third / Rational::new(2, 3), Rational::new(1, 2));
+        assert!(third < Rational::new(1, 2));
+    }
+
+    #[test]
+    fn overflow() {
+        let big = Rational::from(i32::MAX);
+        assert_eq!(big.checked_add(Rational::ONE), None);
+        assert_eq!(big.checked_mul(Rational::from(2)), None);
+        assert_eq!(Rational::ONE.checked_div(Rational::ZERO), None);
+        assert_eq!(
+            big.checked_mul(Rational::new(1, 3)),
+            Some(big / Rational::from(3))
+        );
+        assert_eq!((-big).checked_add(-Rational::ONE), None);
+    }
+
+    #[test]
+    fn approximate() {
+        assert_eq!(Rational::approximate(1. / 3.), Some(Rational::new(1, 3)));
+        assert_eq!(Rational::approximate(-2.5), Some(Rational::new(-5, 2)));
+        assert_eq!(Rational::approximate(4.), Some(Rational::from(4)));
+        assert_eq!(Rational::approximate(std::f64::consts::PI), None);
+        assert_eq!(Rational::approximate(f64::NAN), None);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Rational::new(-1, 3).to_string(), "-1/3");
+        assert_eq!(Rational::from(2).to_string(), "2");
+    }
+}
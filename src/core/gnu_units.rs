@@ -0,0 +1,555 @@
+use super::dimension::Dimensions;
+use super::quantity::{Quantity, QuantityFloat};
+use super::registry;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// The outcome of importing a GNU units `definitions.units` file
+#[derive(Debug, Default)]
+pub struct Import {
+    /// units now in the registry, including those larvae already had
+    pub units: usize,
+    pub prefixes: usize,
+    /// definitions left out, as `file:line: reason`
+    pub skipped: Vec<String>,
+}
+
+/// Adds the prefixes and units of a GNU units file, and of the files it includes, to the
+/// registry. Units larvae cannot represent, such as nonlinear functions, tables or primitive
+/// units without a larvae dimension, are skipped, and so are those that would change the size
+/// of an existing unit.
+pub fn import(file: &Path) -> std::io::Result<Import> {
+    let mut definitions = Definitions::default();
+    definitions.read(file)?;
+    let mut import = Import::default();
+    let mut prefixes: Vec<_> = definitions.prefixes.clone().into_iter().collect();
+    prefixes.sort();
+    for (name, (expr, at)) in prefixes {
+        let added = definitions.evaluate(&expr).and_then(|q| {
+            let factor = q
+                .try_into()
+                .map_err(|_| "prefix with dimensions".to_string())?;
+            registry::define_prefix(&name, factor).map_err(|e| e.to_string())
+        });
+        match added {
+            Ok(()) => import.prefixes += 1,
+            Err(e) => import.skipped.push(format!("{}: {}", at, e)),
+        }
+    }
+    for name in definitions.order.clone() {
+        let at = definitions.units[&name].at.clone();
+        let added = definitions
+            .unit(&name)
+            .and_then(|q| registry::define(&name, &q).map_err(|e| e.to_string()));
+        match added {
+            Ok(()) => import.units += 1,
+            Err(e) => import.skipped.push(format!("{}: {}", at, e)),
+        }
+    }
+    import.skipped.append(&mut definitions.skipped);
+    Ok(import)
+}
+
+#[derive(Debug, Clone)]
+enum Definition {
+    Primitive(Dimensions),
+    Unit(String),
+    Unsupported(&'static str),
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    definition: Definition,
+    // file:line
+    at: String,
+}
+
+#[derive(Debug, Default)]
+struct Definitions {
+    units: HashMap<String, Entry>,
+    // names in the order they are defined
+    order: Vec<String>,
+    prefixes: HashMap<String, (String, String)>,
+    // set with `!set`, unless in the environment
+    variables: HashMap<String, String>,
+    resolved: HashMap<String, Result<Quantity, String>>,
+    resolving: HashSet<String>,
+    skipped: Vec<String>,
+}
+
+// the dimensions of the units GNU units builds everything else from
+fn primitive(name: &str) -> Option<Dimensions> {
+    let mut dimensions = Dimensions::default();
+    match name {
+        "m" => dimensions.length = 1.into(),
+        "kg" => dimensions.mass = 1.into(),
+        "s" => dimensions.time = 1.into(),
+        "K" => dimensions.temperature = 1.into(),
+        "mol" => dimensions.amount = 1.into(),
+        "cd" => dimensions.luminous_intensity = 1.into(),
+        "radian" => dimensions.angle = 1.into(),
+        "sr" => dimensions.angle = 2.into(),
+        "bit" => dimensions.information = 1.into(),
+        "US$" => dimensions.money = 1.into(),
+        "A" => dimensions.current = 1.into(),
+        _ => return None,
+    }
+    Some(dimensions)
+}
+
+impl Definitions {
+    fn read(&mut self, file: &Path) -> std::io::Result<()> {
+        let contents = std::fs::read_to_string(file)?;
+        // whether each `!locale`, `!var` or `!utf8` block we are in applies
+        let mut blocks: Vec<bool> = vec![];
+        let mut lines = contents.lines().enumerate();
+        while let Some((i, line)) = lines.next() {
+            let mut line = uncomment(line).to_string();
+            while let Some(joined) = line.strip_suffix('\\') {
+                line = format!(
+                    "{} {}",
+                    joined,
+                    lines.next().map_or("", |(_, l)| uncomment(l))
+                );
+            }
+            let at = format!("{}:{}", file.display(), i + 1);
+            let line = line.trim();
+            let included = blocks.iter().all(|b| *b);
+            if let Some(command) = line.strip_prefix('!') {
+                let mut words = command.split_whitespace();
+                match (words.next(), words.next()) {
+                    (Some("locale"), locale) => blocks.push(locale == Some("en_US")),
+                    (Some("utf8"), _) => blocks.push(true),
+                    (Some("var"), Some(name)) => blocks.push(self.variable_in(name, words)),
+                    (Some("varnot"), Some(name)) => blocks.push(!self.variable_in(name, words)),
+                    (Some("endlocale" | "endutf8" | "endvar"), _) => {
+                        blocks.pop();
+                    }
+                    (Some("set"), Some(name)) if included => {
+                        let value = words.next().unwrap_or_default().to_string();
+                        self.variables.entry(name.to_string()).or_insert(value);
+                    }
+                    (Some("include"), Some(name)) if included => {
+                        let included = file.parent().unwrap_or(Path::new("")).join(name);
+                        if let Err(e) = self.read(&included) {
+                            self.skipped
+                                .push(format!("{}: {}: {}", at, included.display(), e));
+                        }
+                    }
+                    // messages, unit lists and the like
+                    _ => {}
+                }
+            } else if !line.is_empty() && included {
+                self.define(line, at);
+            }
+        }
+        Ok(())
+    }
+
+    fn variable_in<'a>(&self, name: &str, values: impl Iterator<Item = &'a str>) -> bool {
+        let value = std::env::var(name)
+            .ok()
+            .or_else(|| self.variables.get(name).cloned());
+        value.is_some_and(|value| values.into_iter().any(|v| v == value))
+    }
+
+    fn define(&mut self, line: &str, at: String) {
+        let (name, expr) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        // `+` marks a deliberate redefinition
+        let name = name.strip_prefix('+').unwrap_or(name);
+        let expr = expr.trim();
+        if let Some(prefix) = name.strip_suffix('-') {
+            self.prefixes
+                .insert(prefix.to_string(), (expr.to_string(), at));
+            return;
+        }
+        let (name, definition) = if let Some((function, _)) = name.split_once('(') {
+            (function, Definition::Unsupported("nonlinear function"))
+        } else if let Some((table, _)) = name.split_once('[') {
+            (table, Definition::Unsupported("table"))
+        } else if expr.starts_with('!') {
+            let definition = primitive(name).map_or(
+                Definition::Unsupported("primitive unit without a larvae dimension"),
+                Definition::Primitive,
+            );
+            (name, definition)
+        } else {
+            (name, Definition::Unit(expr.to_string()))
+        };
+        if !self.units.contains_key(name) {
+            self.order.push(name.to_string());
+        }
+        self.units
+            .insert(name.to_string(), Entry { definition, at });
+    }
+
+    /// The size of a unit as it would be looked up in GNU units
+    fn unit(&mut self, name: &str) -> Result<Quantity, String> {
+        if let Some(resolved) = self.resolved.get(name) {
+            return resolved.clone();
+        }
+        if !self.resolving.insert(name.to_string()) {
+            return Err(format!("`{}` is defined in terms of itself", name));
+        }
+        let plurals = [("ies", "y"), ("es", ""), ("s", "")]
+            .into_iter()
+            .filter_map(|(plural, singular)| {
+                Some(name.strip_suffix(plural)?.to_string() + singular)
+            });
+        let resolved = std::iter::once(name.to_string())
+            .chain(plurals)
+            .find_map(|name| self.unit_or_prefixed(&name))
+            .unwrap_or_else(|| Err(format!("unknown unit `{}`", name)));
+        self.resolving.remove(name);
+        self.resolved.insert(name.to_string(), resolved.clone());
+        resolved
+    }
+
+    fn unit_or_prefixed(&mut self, name: &str) -> Option<Result<Quantity, String>> {
+        if let Some(entry) = self.units.get(name) {
+            return Some(match entry.definition.clone() {
+                Definition::Primitive(dimensions) => Ok(Quantity {
+                    dimensions,
+                    ..1.into()
+                }),
+                Definition::Unit(expr) => self.evaluate(&expr),
+                Definition::Unsupported(what) => Err(format!("`{}` is a {}", name, what)),
+            });
+        }
+        if let Some((expr, _)) = self.prefixes.get(name).cloned() {
+            return Some(self.evaluate(&expr));
+        }
+        // the longest prefix that leaves the name of a unit
+        let (prefix, rest) = self
+            .prefixes
+            .keys()
+            .filter_map(|prefix| Some((prefix.clone(), name.strip_prefix(prefix.as_str())?)))
+            .filter(|(_, rest)| self.units.contains_key(*rest))
+            .max_by_key(|(prefix, _)| prefix.len())?;
+        let rest = rest.to_string();
+        Some(
+            self.evaluate(&self.prefixes[&prefix].0.clone())
+                .and_then(|factor| Ok(factor.mul(&self.unit(&rest)?))),
+        )
+    }
+
+    fn evaluate(&mut self, expr: &str) -> Result<Quantity, String> {
+        let tokens = tokenize(expr)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+            definitions: self,
+        };
+        let q = parser.sum()?;
+        match tokens.get(parser.pos) {
+            Some(token) => Err(format!("unexpected {:?} in `{}`", token, expr)),
+            None => Ok(q),
+        }
+    }
+}
+
+fn uncomment(line: &str) -> &str {
+    line.split('#').next().unwrap_or_default()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(QuantityFloat),
+    Name(String),
+    Op(char),
+}
+
+const OPERATORS: &str = "+-*/|^()";
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = expr.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                let exponent_sign = (c == '-' || c == '+') && number.ends_with(['e', 'E']);
+                if !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign) {
+                    break;
+                }
+                number.push(c);
+                chars.next();
+            }
+            let value = number
+                .parse()
+                .map_err(|_| format!("invalid number `{}`", number))?;
+            tokens.push(Token::Number(value));
+        } else if OPERATORS.contains(c) {
+            chars.next();
+            // `**` is a power like `^`
+            if c == '*' && chars.peek() == Some(&'*') {
+                chars.next();
+                tokens.push(Token::Op('^'));
+            } else {
+                tokens.push(Token::Op(c));
+            }
+        } else {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || OPERATORS.contains(c) {
+                    break;
+                }
+                name.push(c);
+                chars.next();
+            }
+            // a name ending in a digit from 2 to 9 is raised to it, as in `cm3`
+            let power = name
+                .chars()
+                .last()
+                .and_then(|c| c.to_digit(10))
+                .filter(|d| *d >= 2 && name.len() > 1)
+                .filter(|_| !name[..name.len() - 1].ends_with(|c: char| c.is_ascii_digit()));
+            match power {
+                Some(power) => {
+                    name.pop();
+                    tokens.push(Token::Name(name));
+                    tokens.push(Token::Op('^'));
+                    tokens.push(Token::Number(power.into()));
+                }
+                None if name == "per" => tokens.push(Token::Op('/')),
+                None => tokens.push(Token::Name(name)),
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// Precedence from lowest: + and -, * and /, multiplication by juxtaposition, unary -, ^, |
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    definitions: &'a mut Definitions,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+    fn accept(&mut self, op: char) -> bool {
+        let accepted = self.peek() == Some(&Token::Op(op));
+        self.pos += accepted as usize;
+        accepted
+    }
+    fn sum(&mut self) -> Result<Quantity, String> {
+        let mut q = self.term()?;
+        loop {
+            q = if self.accept('+') {
+                q.add(&self.term()?).map_err(|e| e.to_string())?
+            } else if self.accept('-') {
+                q.sub(&self.term()?).map_err(|e| e.to_string())?
+            } else {
+                return Ok(q);
+            };
+        }
+    }
+    fn term(&mut self) -> Result<Quantity, String> {
+        let mut q = self.product()?;
+        loop {
+            q = if self.accept('*') {
+                q.mul(&self.product()?)
+            } else if self.accept('/') {
+                q.div(&self.product()?)
+            } else {
+                return Ok(q);
+            };
+        }
+    }
+    fn product(&mut self) -> Result<Quantity, String> {
+        let mut q = self.unary()?;
+        while matches!(
+            self.peek(),
+            Some(Token::Number(_) | Token::Name(_) | Token::Op('('))
+        ) {
+            q = q.mul(&self.unary()?);
+        }
+        Ok(q)
+    }
+    fn unary(&mut self) -> Result<Quantity, String> {
+        if self.accept('-') {
+            Ok(self.unary()?.neg())
+        } else {
+            self.power()
+        }
+    }
+    fn power(&mut self) -> Result<Quantity, String> {
+        let q = self.atom()?;
+        if !self.accept('^') {
+            return Ok(q);
+        }
+        let exponent: QuantityFloat = self
+            .unary()?
+            .try_into()
+            .map_err(|_| "exponent with dimensions".to_string())?;
+        q.pow(exponent).map_err(|e| e.to_string())
+    }
+    fn atom(&mut self) -> Result<Quantity, String> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Number(n)) if self.accept('|') => match self.peek().cloned() {
+                Some(Token::Number(d)) => {
+                    self.pos += 1;
+                    Ok((n / d).into())
+                }
+                _ => Err("expected a number after `|`".to_string()),
+            },
+            Some(Token::Number(n)) => Ok(n.into()),
+            Some(Token::Op('(')) => {
+                let q = self.sum()?;
+                self.accept(')')
+                    .then_some(q)
+                    .ok_or_else(|| "expected `)`".to_string())
+            }
+            Some(Token::Name(name)) if name == "sqrt" || name == "cuberoot" => {
+                let q = self.atom()?;
+                q.pow(if name == "sqrt" { 0.5 } else { 1. / 3. })
+                    .map_err(|e| e.to_string())
+            }
+            Some(Token::Name(name)) => self.definitions.unit(&name),
+            token => Err(format!("unexpected {:?}", token)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::unit::Units;
+
+    const DEFINITIONS: &str = "
+# primitive units
+m          !
+kg         !
+s          !
+K          !
+mol        !
+wholenote  !
+radian     !dimensionless
+
+!set UNITS_ENGLISH US
+
+kilo-      1e3
+k-         kilo
+c-         1|100
+gram       1|1000 kg
+g          gram
+meter      m
+second     s
+minute     60 s
+min        minute
+hour       60 min
+inch       2.54 cm
+foot       12 inch
+ft         foot
+N          kg m / s^2
+J          N m
+yard       0.9 m
+gnu_gallon 231 in^3
+in         inch
+gnu_cc     cm3
+gnu_knot   1852 m per hour
+gnu_acre   43560 ft2
+tempC(x)   units=[1;K] x K + 273.15 ; (tempC - 273.15 K) / K
+gnu_loop   2 gnu_loop
+wholenote_half 1|2 wholenote
+!var UNITS_ENGLISH US
+gnu_pint   1|8 gnu_gallon
+!endvar
+!var UNITS_ENGLISH GB
+gnu_pint   1|4 gnu_gallon
+!endvar
+!locale en_GB
+gnu_quart  2 gnu_pint
+!endlocale
+hour       3600 \\
+           s
+";
+
+    // a file of its own for each test run, as tests run in parallel
+    fn fixture(name: &str, contents: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("larvae_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join(name);
+        std::fs::write(&file, contents).unwrap();
+        file
+    }
+
+    fn si(name: &str) -> QuantityFloat {
+        registry::unit(name).unwrap().rescale(&Units::SI()).value
+    }
+
+    #[test]
+    fn import_definitions() {
+        let import = import(&fixture("definitions.units", DEFINITIONS)).unwrap();
+        assert!((si("gnu_gallon") - 3.785411784e-3).abs() < 1e-15);
+        assert!((si("gnu_pint") - 4.73176473e-4).abs() < 1e-15);
+        assert!((si("gnu_cc") - 1e-6).abs() < 1e-18);
+        assert!((si("gnu_knot") - 1852. / 3600.).abs() < 1e-12);
+        assert!((si("gnu_acre") - 4046.8564224).abs() < 1e-9);
+        // units are found with prefixes and in the plural
+        assert!((si("kilogram") - 1.).abs() < 1e-15);
+        assert!((si("kgnu_gallon") - 3.785411784).abs() < 1e-12);
+        assert_eq!(registry::unit("gnu_quart"), None);
+        let skipped: Vec<_> = import
+            .skipped
+            .iter()
+            .map(|e| e.split_once(": ").unwrap().1)
+            .collect();
+        for reason in [
+            "primitive unit without a larvae dimension",
+            "nonlinear function",
+            "defined in terms of itself",
+            "`yard` is already a unit of another size",
+        ] {
+            assert!(skipped.iter().any(|e| e.contains(reason)), "{}", reason);
+        }
+    }
+
+    #[test]
+    fn import_included_file() {
+        fixture(
+            "included.units",
+            "inc_liter  1e-3 m^3\ninc_barrel 159 inc_liters\ninc_tempF(x) units=[1;K] x K\n",
+        );
+        let file = fixture(
+            "including.units",
+            "m !\nkilo- 1e3\n!include included.units\n",
+        );
+        let import = import(&file).unwrap();
+        assert!((si("inc_barrel") - 0.159).abs() < 1e-15);
+        assert!((si("kiloinc_barrel") - 159.).abs() < 1e-12);
+        assert_eq!(registry::unit("inc_tempF"), None);
+        assert_eq!(import.skipped.len(), 1);
+        assert!(import.skipped[0].contains("included.units:3: `inc_tempF` is a nonlinear function"));
+    }
+
+    #[test]
+    fn expressions() {
+        let mut definitions = Definitions::default();
+        definitions.define("m !", "".to_string());
+        definitions.define("s !", "".to_string());
+        let mut eval = |expr| definitions.evaluate(expr).unwrap();
+        assert_eq!(eval("1|4 m^2").value, 0.25);
+        // juxtaposition binds tighter than `/`, `*` does not
+        assert_eq!(eval("2 m / 4 s s").dimensions.time, (-2).into());
+        assert_eq!(eval("2 m / 4 s * s").dimensions.time, 0.into());
+        assert_eq!(eval("sqrt(16 m2)").value, 4.);
+        assert_eq!(eval("3 ** 2 - -1").value, 10.);
+        assert_eq!(eval("1.5e-3 m").value, 0.0015);
+        assert_eq!(
+            definitions.evaluate("m^1.2345").unwrap_err(),
+            "cannot raise a quantity with dimensions to the power 1.2345"
+        );
+        assert_eq!(
+            definitions.evaluate("((m^30000)^30000)^3").unwrap_err(),
+            "dimension exponents out of range in the power 3"
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use super::dimension::Dimensions;
+use super::quantity::{Quantity, QuantityFloat};
+use super::unit::Units;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+/// Logarithmic units. A level is stored as the linear power ratio (or power, for levels
+/// with a reference) it stands for, and `Units::level` only records how to show it.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    decibel,
+    bel,
+    neper,
+    decibel_milliwatt,
+    decibel_watt,
+}
+
+pub const LEVELS: &[Level] = &[
+    Level::decibel,
+    Level::bel,
+    Level::neper,
+    Level::decibel_milliwatt,
+    Level::decibel_watt,
+];
+
+lazy_static! {
+    pub static ref LEVELS_LOOKUP: HashMap<&'static str, Level> = LEVELS
+        .iter()
+        .flat_map(|l| [(l.name(), *l), (l.abbrev(), *l), (l.symbol(), *l)])
+        .collect();
+}
+
+impl Level {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::decibel => "decibel",
+            Self::bel => "bel",
+            Self::neper => "neper",
+            Self::decibel_milliwatt => "decibel_milliwatt",
+            Self::decibel_watt => "decibel_watt",
+        }
+    }
+    pub fn abbrev(&self) -> &'static str {
+        match self {
+            Self::decibel => "dB",
+            // `B` is the byte
+            Self::bel => "bel",
+            Self::neper => "Np",
+            Self::decibel_milliwatt => "dBm",
+            Self::decibel_watt => "dBW",
+        }
+    }
+    pub fn symbol(&self) -> &'static str {
+        self.abbrev()
+    }
+    /// The power in watts at 0 on this scale, `None` for plain ratios
+    pub fn reference(&self) -> Option<QuantityFloat> {
+        match self {
+            Self::decibel_milliwatt => Some(1e-3),
+            Self::decibel_watt => Some(1.),
+            _ => None,
+        }
+    }
+    /// Levels relative to a fixed power, which cannot be added to each other
+    pub fn is_absolute(&self) -> bool {
+        self.reference().is_some()
+    }
+    pub fn dimensions(&self) -> Dimensions {
+        if self.is_absolute() {
+            Dimensions {
+                length: 2.into(),
+                mass: 1.into(),
+                time: (-3).into(),
+                ..Default::default()
+            }
+        } else {
+            Dimensions::default()
+        }
+    }
+    fn to_ratio(self, level: QuantityFloat) -> QuantityFloat {
+        match self {
+            Self::bel => QuantityFloat::powf(10., level),
+            // 1 Np is a field ratio of e, so a power ratio of e²
+            Self::neper => (2. * level).exp(),
+            _ => QuantityFloat::powf(10., level / 10.),
+        }
+    }
+    fn level_of(self, ratio: QuantityFloat) -> QuantityFloat {
+        match self {
+            Self::bel => ratio.log10(),
+            Self::neper => ratio.ln() / 2.,
+            _ => 10. * ratio.log10(),
+        }
+    }
+    /// `level` on this scale, e.g. `Level::decibel_milliwatt.quantity(30.)` is 1 W
+    pub fn quantity(&self, level: QuantityFloat) -> Quantity {
+        Quantity {
+            value: self.to_ratio(level) * self.reference().unwrap_or(1.),
+            dimensions: self.dimensions(),
+            units: Units {
+                level: Some(*self),
+                ..Units::SI()
+            },
+        }
+    }
+    /// Reading of `q` on this scale
+    pub fn of(&self, q: &Quantity) -> QuantityFloat {
+        let linear = q.rescale(&Units::SI()).value;
+        self.level_of(linear / self.reference().unwrap_or(1.))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for level in LEVELS {
+            let q = level.quantity(3.);
+            assert!((level.of(&q) - 3.).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn ratios() {
+        assert!((Level::decibel.quantity(30.).value - 1000.).abs() < 1e-9);
+        assert!((Level::decibel_milliwatt.quantity(30.).value - 1.).abs() < 1e-12);
+        assert!((Level::decibel.of(&Level::neper.quantity(1.)) - 8.685889638).abs() < 1e-9);
+        assert_eq!(Level::bel.of(&Level::decibel.quantity(20.)), 2.);
+    }
+}
@@ -1,4 +1,8 @@
 use crate::core::dimension::*;
+use crate::core::level::Level;
+use crate::core::system::UnitSystem;
+use crate::core::unit::angle::Angle;
+use crate::core::unit::temperature::Temperature;
 use crate::core::unit::*;
 
 pub type QuantityFloat = f64;
@@ -7,6 +11,88 @@ mod display;
 pub mod into;
 mod ops;
 
+pub use display::DisplayOptions;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuantityError {
+    // boxed, dimensions grow with every base dimension
+    Dimension(Box<DimensionError>),
+    AbsoluteTemperatureSum,
+    AbsoluteTemperatureSubtrahend,
+    TemperatureKind,
+    Levels(Level, Level),
+    // symbol and system of each unit
+    DerivedSystems((&'static str, &'static str), (&'static str, &'static str)),
+    Exponent(QuantityFloat),
+    ExponentOverflow(QuantityFloat),
+    ShiftedScale(Temperature),
+}
+impl From<DimensionError> for QuantityError {
+    fn from(e: DimensionError) -> Self {
+        Self::Dimension(Box::new(e))
+    }
+}
+impl std::fmt::Display for QuantityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Dimension(e) => {
+                write!(f, "incompatible dimensions {} and {}", e.0, e.1)
+            }
+            Self::AbsoluteTemperatureSum => {
+                write!(f, "cannot add two absolute temperatures")
+            }
+            Self::AbsoluteTemperatureSubtrahend => {
+                write!(
+                    f,
+                    "cannot subtract an absolute temperature from a difference"
+                )
+            }
+            Self::TemperatureKind => {
+                write!(
+                    f,
+                    "cannot convert between absolute temperatures and temperature differences"
+                )
+            }
+            Self::Levels(l, r) => {
+                write!(
+                    f,
+                    "cannot combine levels in {} and {}",
+                    l.symbol(),
+                    r.symbol()
+                )
+            }
+            Self::DerivedSystems(l, r) => {
+                write!(
+                    f,
+                    "cannot combine {} and {}, which measure {} and {}",
+                    l.0,
+                    r.0,
+                    l.1.replace('_', " "),
+                    r.1.replace('_', " ")
+                )
+            }
+            Self::Exponent(x) => {
+                write!(
+                    f,
+                    "cannot raise a quantity with dimensions to the power {}",
+                    x
+                )
+            }
+            Self::ExponentOverflow(x) => {
+                write!(f, "dimension exponents out of range in the power {}", x)
+            }
+            Self::ShiftedScale(t) => {
+                write!(
+                    f,
+                    "cannot multiply a reading in {}, convert it to {} first",
+                    t.symbol(),
+                    t.thermodynamic().symbol()
+                )
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct Quantity {
     pub value: QuantityFloat,
@@ -22,6 +108,28 @@ impl Quantity {
         }
     }
     pub fn set_units(&self, units: &Units) -> Self {
+        if self.units == *units {
+            return *self;
+        }
+        // a lone temperature is a reading on its scale, so the zero points matter
+        let (from, to) = if self.dimensions.is_temperature() {
+            (self.units.temperature.offset(), units.temperature.offset())
+        } else {
+            (0., 0.)
+        };
+        Self {
+            units: *units,
+            value: (self.value + from) * Self::conversion_factor(&self.units, &self.dimensions)
+                / Self::conversion_factor(units, &self.dimensions)
+                - to,
+            dimensions: self.dimensions,
+        }
+    }
+    /// Like `set_units`, but a temperature is taken as a difference, ignoring the zero points
+    pub fn rescale(&self, units: &Units) -> Self {
+        if self.units == *units {
+            return *self;
+        }
         Self {
             units: *units,
             value: self.value * Self::conversion_factor(&self.units, &self.dimensions)
@@ -29,13 +137,79 @@ impl Quantity {
             dimensions: self.dimensions,
         }
     }
+    /// Expresses the quantity with `angle` as the exponent of its angle dimension,
+    /// counting radians as dimensionless
+    pub fn with_angle(&self, angle: Rational) -> Self {
+        let radians = self.rescale(&Units {
+            angle: Angle::radian,
+            ..self.units
+        });
+        Self {
+            dimensions: Dimensions {
+                angle,
+                ..self.dimensions
+            },
+            ..radians
+        }
+    }
+    /// Conversion for `->`, which also gives the result the target's angle dimension
+    pub fn convert(&self, target: &Self) -> Result<Self, QuantityError> {
+        if !self.dimensions.is_compatible(&target.dimensions) {
+            return Err(DimensionError(self.dimensions, target.dimensions).into());
+        }
+        if self.dimensions.is_temperature()
+            && self.units.temperature.is_absolute() != target.units.temperature.is_absolute()
+        {
+            return Err(QuantityError::TemperatureKind);
+        }
+        Ok(self
+            .with_angle(target.dimensions.angle)
+            .set_units(&target.units))
+    }
+    /// The quantity in the base units of `system`, in which a temperature difference stays one
+    pub fn in_system(&self, system: UnitSystem) -> Self {
+        let units = system.units();
+        let temperature = if self.units.temperature.is_difference() {
+            units.temperature.difference()
+        } else {
+            units.temperature
+        };
+        self.set_units(&Units {
+            temperature,
+            ..units
+        })
+    }
+    pub fn is_absolute_temperature(&self) -> bool {
+        self.dimensions.is_temperature() && self.units.temperature.is_absolute()
+    }
+    /// Fails for readings on scales that do not start at absolute zero, as 2 * 20 °C could be
+    /// 40 °C or 586.3 K
+    pub fn as_factor(&self) -> Result<Self, QuantityError> {
+        if self.is_absolute_temperature() && self.units.temperature.offset() != 0. {
+            return Err(QuantityError::ShiftedScale(self.units.temperature));
+        }
+        Ok(*self)
+    }
 
     fn conversion_factor(units: &Units, dimensions: &Dimensions) -> QuantityFloat {
-        let l = units.length.conversion_factor().powf(dimensions.length);
-        let m = units.mass.conversion_factor().powf(dimensions.mass);
-        let t = units.time.conversion_factor().powf(dimensions.time);
-
-        l * m * t
+        // units of absent dimensions are never consulted, so money needs no rates unless present
+        fn factor(unit: &dyn Unit, exponent: Rational) -> QuantityFloat {
+            if exponent == Rational::ZERO {
+                1.
+            } else {
+                unit.conversion_factor().powf(exponent.to_f64())
+            }
+        }
+        factor(&units.length, dimensions.length)
+            * factor(&units.mass, dimensions.mass)
+            * factor(&units.time, dimensions.time)
+            * factor(&units.temperature, dimensions.temperature)
+            * factor(&units.amount, dimensions.amount)
+            * factor(&units.luminous_intensity, dimensions.luminous_intensity)
+            * factor(&units.angle, dimensions.angle)
+            * factor(&units.information, dimensions.information)
+            * factor(&units.money, dimensions.money)
+            * factor(&units.current, dimensions.current)
     }
 }
 
@@ -43,15 +217,31 @@ impl Quantity {
 mod test {
 
     use super::*;
+    use crate::core::unit::amount::Amount::*;
     use crate::core::unit::length::Length::*;
+    use crate::core::unit::temperature::Temperature::{self, *};
     use crate::core::unit::Units;
 
+    fn temperature(value: QuantityFloat, unit: Temperature) -> Quantity {
+        Quantity {
+            value,
+            dimensions: Dimensions {
+                temperature: 1.into(),
+                ..Default::default()
+            },
+            units: Units {
+                temperature: unit,
+                ..Units::SI()
+            },
+        }
+    }
+
     #[test]
     pub fn test_conversion_factor() {
         let m = Quantity {
             value: 2000.,
             dimensions: Dimensions {
-                length: 1.,
+                length: 1.into(),
                 ..Default::default()
             },
             ..Default::default()
@@ -65,11 +255,25 @@ mod test {
             },
             value: 2000.,
             dimensions: Dimensions {
-                length: 3.,
+                length: 3.into(),
                 ..Default::default()
             },
         };
         assert_eq!(1e9, Quantity::conversion_factor(&m.units, &m.dimensions));
+        let m = Quantity {
+            units: Units {
+                temperature: millikelvin,
+                amount: kilomole,
+                ..Units::SI()
+            },
+            value: 1.,
+            dimensions: Dimensions {
+                temperature: 1.into(),
+                amount: (-1).into(),
+                ..Default::default()
+            },
+        };
+        assert_eq!(1e-6, Quantity::conversion_factor(&m.units, &m.dimensions));
     }
 
     #[test]
@@ -77,7 +281,7 @@ mod test {
         let m = Quantity {
             value: 2000.,
             dimensions: Dimensions {
-                length: 1.,
+                length: 1.into(),
                 ..Default::default()
             },
             ..Default::default()
@@ -98,7 +302,7 @@ mod test {
                     ..Units::SI()
                 },
                 dimensions: Dimensions {
-                    length: 1.,
+                    length: 1.into(),
                     ..Default::default()
                 },
                 value: 2.
@@ -106,14 +310,52 @@ mod test {
             converted
         )
     }
+    #[test]
+    pub fn convert_temperatures() {
+        let f = temperature(20., celsius).set_units(&Units {
+            temperature: fahrenheit,
+            ..Units::SI()
+        });
+        assert!((f.value - 68.).abs() < 1e-9);
+        let k = temperature(-40., fahrenheit).set_units(&Units::SI());
+        assert!((k.value - 233.15).abs() < 1e-9);
+        let r = temperature(0., celsius).set_units(&Units {
+            temperature: rankine,
+            ..Units::SI()
+        });
+        assert!((r.value - 491.67).abs() < 1e-9);
+    }
+
+    #[test]
+    pub fn convert_temperature_differences() {
+        let f = temperature(10., delta_celsius).set_units(&Units {
+            temperature: delta_fahrenheit,
+            ..Units::SI()
+        });
+        assert!((f.value - 18.).abs() < 1e-9);
+        // temperatures inside compound quantities are always differences
+        let per_celsius = Quantity {
+            value: 9.,
+            dimensions: Dimensions {
+                temperature: (-1).into(),
+                ..Default::default()
+            },
+            units: Units {
+                temperature: delta_fahrenheit,
+                ..Units::SI()
+            },
+        };
+        assert!((per_celsius.set_units(&Units::SI()).value - 16.2).abs() < 1e-9);
+    }
+
     #[test]
     #[should_panic]
     pub fn incompatible_dimensions() {
         let m = Quantity {
             value: 2.,
             dimensions: Dimensions {
-                length: 3.,
-                mass: 1.,
+                length: 3.into(),
+                mass: 1.into(),
                 ..Default::default()
             },
             ..Default::default()
@@ -125,8 +367,8 @@ mod test {
             },
             value: -20.,
             dimensions: Dimensions {
-                length: 1.,
-                mass: 3.,
+                length: 1.into(),
+                mass: 3.into(),
                 ..Default::default()
             },
         };
@@ -138,8 +380,8 @@ mod test {
         let q = Quantity {
             value: 25.,
             dimensions: Dimensions {
-                length: 1.,
-                mass: 2.,
+                length: 1.into(),
+                mass: 2.into(),
                 ..Default::default()
             },
             ..Default::default()
@@ -148,9 +390,10 @@ mod test {
         let q = Quantity {
             value: 25.,
             dimensions: Dimensions {
-                length: 1.,
-                mass: 2.,
-                time: -3.,
+                length: 1.into(),
+                mass: 2.into(),
+                time: (-3).into(),
+                ..Default::default()
             },
             ..Default::default()
         };
@@ -160,8 +403,8 @@ mod test {
         let q = Quantity {
             value: 25.,
             dimensions: Dimensions {
-                length: -1.,
-                mass: -2.,
+                length: (-1).into(),
+                mass: (-2).into(),
                 ..Default::default()
             },
             ..Default::default()
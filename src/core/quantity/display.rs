@@ -1,52 +1,117 @@
-use super::Quantity;
+use super::{Quantity, QuantityFloat};
+use crate::core::dimension::{Dimensions, Rational};
 use crate::core::unit::*;
 use float_pretty_print::PrettyPrintFloat;
 use std::fmt::Display;
 
 macro_rules! sym_dim {
     ($f:ident, $map: ident, $sel:ident, $($dim:ident),+) => {
-        let mut $map: Vec<(&str, f64)> = vec![
+        let mut $map: Vec<(&str, Rational)> = vec![
             $(($sel.units.$dim.symbol(),  $sel.dimensions.$dim)),+
         ];
     }
 }
 
+/// How quantities are written out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayOptions {
+    /// Show results with the dimensions of a derived unit in it, e.g. kg·m/s² as N
+    pub named_units: bool,
+    /// Put the metric prefix on a lone unit that keeps the value between 1 and 1000, e.g. 12 μm
+    pub prefixes: bool,
+}
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            named_units: true,
+            prefixes: false,
+        }
+    }
+}
+
+pub struct QuantityDisplay<'a> {
+    quantity: &'a Quantity,
+    options: DisplayOptions,
+}
+
+impl Quantity {
+    pub fn display(&self, options: DisplayOptions) -> QuantityDisplay<'_> {
+        QuantityDisplay {
+            quantity: self,
+            options,
+        }
+    }
+    /// The derived unit to show the quantity in, if a single derived system has its dimensions
+    /// and that system's first unit is 1 in SI units. Powers of a single unit like m³ are
+    /// already short and are left alone.
+    fn named_unit(&self) -> Option<DerivedUnit> {
+        let mut units = DERIVED_UNITS
+            .iter()
+            .filter(|unit| unit.dimensions == self.dimensions);
+        let unit = units.next()?;
+        if units.next().is_some() || unit.dimensions.base_count() < 2 {
+            return None;
+        }
+        Some(*unit).filter(|unit| unit.conversion_factor == 1.)
+    }
+}
+
 impl Display for Quantity {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        sym_dim!(f, sym_dim, self, length, mass, time);
-        sym_dim.sort_by(|l, r| r.1.partial_cmp(&l.1).unwrap());
+        self.display(DisplayOptions::default()).fmt(f)
+    }
+}
+
+impl Display for QuantityDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let q = self.quantity;
+        if let Some(level) = q.units.level {
+            return write!(f, "{} {}", number(level.of(q)), level.symbol());
+        }
+        let named = || {
+            q.named_unit()
+                .filter(|_| self.options.named_units && q.units.system.is_none())
+        };
+        if let Some(unit) = q.units.derived.or_else(named) {
+            let value = q.rescale(&Units::SI()).value / unit.conversion_factor;
+            return self.write_unit(f, value, unit.symbol);
+        }
+        sym_dim!(
+            f,
+            sym_dim,
+            q,
+            length,
+            mass,
+            time,
+            temperature,
+            amount,
+            luminous_intensity,
+            angle,
+            information,
+            money,
+            current
+        );
+        sym_dim.sort_by_key(|(_, dim)| std::cmp::Reverse(*dim));
 
         let mut pos_dims = vec![];
         let mut neg_dims = vec![];
         for (sym, dim) in sym_dim {
-            if dim == 0. {
-                continue;
-            }
-            if dim.is_sign_positive() {
+            if dim.is_positive() {
                 pos_dims.push((sym, dim))
-            } else if dim.is_sign_negative() {
+            } else if dim.is_negative() {
                 neg_dims.insert(0, (sym, -dim))
             }
         }
-        let mut pos_units = vec![];
-        for (sym, dim) in pos_dims.clone() {
-            pos_units.push(if dim == 1.0 {
-                sym.to_string()
-            } else if dim == (dim as i64) as f64 {
-                format!("{}{}", sym, num_to_superscript(dim as i64))
-            } else {
-                format!("{}^({})", sym, dim)
-            });
-        }
-        let mut neg_units = vec![];
-        for (sym, dim) in neg_dims.clone() {
-            neg_units.push(if dim == 1.0 {
-                sym.to_string()
-            } else if dim == (dim as i64) as f64 {
-                format!("{}{}", sym, num_to_superscript(dim as i64))
-            } else {
-                format!("{}^({})", sym, dim)
-            });
+        let pos_units: Vec<String> = pos_dims
+            .iter()
+            .map(|(sym, dim)| unit_power(sym, *dim))
+            .collect();
+        let neg_units: Vec<String> = neg_dims
+            .iter()
+            .map(|(sym, dim)| unit_power(sym, *dim))
+            .collect();
+        if let ([(sym, Rational::ONE)], []) = (&pos_dims[..], &neg_dims[..]) {
+            return self.write_unit(f, q.value, sym);
         }
         let units: String = match (pos_dims.len(), neg_dims.len()) {
             (0, 0) => String::new(),
@@ -55,12 +120,97 @@ impl Display for Quantity {
             (_, _) => format!("{}/{}", pos_units.join("·"), neg_units.join("·")),
         };
 
-        let value = self.value;
-        if value == (value as i64) as f64 {
-            write!(f, "{} {}", value, units)
-        } else {
-            write!(f, "{:.10} {}", PrettyPrintFloat(value), units)
+        write!(f, "{} {}", number(q.value), units)
+    }
+}
+
+impl QuantityDisplay<'_> {
+    fn write_unit(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        value: QuantityFloat,
+        symbol: &str,
+    ) -> std::fmt::Result {
+        if self.options.prefixes {
+            if let Some((value, symbol)) = with_prefix(value, symbol) {
+                return write!(f, "{} {}", number(value), symbol);
+            }
         }
+        write!(f, "{} {}", number(value), symbol)
+    }
+}
+
+// size in SI units of a unit that is declared with this symbol
+fn size(symbol: &str) -> Option<(Dimensions, QuantityFloat)> {
+    let q = UNITS_LOOKUP.get(symbol)?;
+    Some((q.dimensions, q.rescale(&Units::SI()).value))
+}
+
+/// The unit `base` and its declared versions with a prefix that is a power of 1000, by size
+fn prefix_family(base: &str) -> Vec<(String, QuantityFloat)> {
+    let prefixed: Vec<(String, i32)> = METRIC_PREFIXES
+        .iter()
+        .filter(|(_, _, _, pow)| pow % 3 == 0)
+        .map(|(_, _, sym, pow)| (format!("{}{}", sym, base), *pow))
+        .filter(|(sym, _)| UNITS_LOOKUP.contains_key(sym.as_str()))
+        .collect();
+    if prefixed.is_empty() {
+        return vec![];
+    }
+    let Some((dims, base_size)) = size(base) else {
+        return vec![];
+    };
+    let mut family: Vec<(String, QuantityFloat)> = prefixed
+        .into_iter()
+        .filter_map(|(sym, pow)| {
+            let (d, s) = size(&sym)?;
+            // a unit that merely looks prefixed, like `Pa` is not a peta-are
+            let scale = s / base_size / QuantityFloat::powi(10., pow);
+            (d == dims && (scale - 1.).abs() < 1e-9).then_some((sym, s))
+        })
+        .collect();
+    family.push((base.to_string(), base_size));
+    family.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    family
+}
+
+/// `value` in the prefixed version of `symbol` that keeps it between 1 and 1000, if it has any
+fn with_prefix(value: QuantityFloat, symbol: &str) -> Option<(QuantityFloat, String)> {
+    if value == 0. || !value.is_finite() {
+        return None;
+    }
+    let family = METRIC_PREFIXES
+        .iter()
+        .filter_map(|(_, _, sym, _)| symbol.strip_prefix(sym))
+        .chain([symbol])
+        .map(prefix_family)
+        .find(|family| family.iter().any(|(sym, _)| sym == symbol))?;
+    let si_value = value * family.iter().find(|(sym, _)| sym == symbol)?.1;
+    let (sym, size) = family
+        .iter()
+        .rev()
+        .find(|(_, size)| (si_value / size).abs() >= 1. - 1e-9)
+        .unwrap_or(&family[0]);
+    Some((si_value / size, sym.clone()))
+}
+
+fn number(value: QuantityFloat) -> String {
+    if value == (value as i64) as f64 {
+        value.to_string()
+    } else {
+        let pretty = format!("{:.10}", PrettyPrintFloat(value));
+        // rounding error such as 68.00000000000006 is shown as a whole number
+        pretty.strip_suffix(".0").unwrap_or(&pretty).to_string()
+    }
+}
+
+fn unit_power(sym: &str, dim: Rational) -> String {
+    if dim == Rational::ONE {
+        sym.to_string()
+    } else if dim.is_integer() {
+        format!("{}{}", sym, num_to_superscript(dim.numer() as i64))
+    } else {
+        format!("{}^({})", sym, dim)
     }
 }
 
@@ -115,8 +265,8 @@ mod tests {
         let q = Quantity {
             value: 25.,
             dimensions: Dimensions {
-                length: 1.,
-                mass: 2.,
+                length: 1.into(),
+                mass: 2.into(),
                 ..Default::default()
             },
             ..Default::default()
@@ -125,9 +275,10 @@ mod tests {
         let q = Quantity {
             value: 25.,
             dimensions: Dimensions {
-                length: 1.,
-                mass: 2.,
-                time: -3.,
+                length: 1.into(),
+                mass: 2.into(),
+                time: (-3).into(),
+                ..Default::default()
             },
             ..Default::default()
         };
@@ -137,12 +288,103 @@ mod tests {
         let q = Quantity {
             value: 25.,
             dimensions: Dimensions {
-                length: -1.,
-                mass: -2.,
+                length: (-1).into(),
+                mass: (-2).into(),
                 ..Default::default()
             },
             ..Default::default()
         };
         assert_eq!(q.to_string(), "25 1/kg²·m");
     }
+
+    #[test]
+    pub fn base_dimensions_display() {
+        let q = Quantity {
+            value: 8.314,
+            dimensions: Dimensions {
+                length: 2.into(),
+                mass: 1.into(),
+                time: (-2).into(),
+                temperature: (-1).into(),
+                amount: (-1).into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(q.to_string(), "8.314 m²·kg/s²·mol·K");
+        let q = Quantity {
+            value: 3.,
+            dimensions: Dimensions {
+                luminous_intensity: 1.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(q.to_string(), "3 cd");
+    }
+
+    #[test]
+    pub fn named_units() {
+        let q = Quantity {
+            value: 2.,
+            dimensions: Dimensions {
+                length: 1.into(),
+                mass: 1.into(),
+                time: (-2).into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(q.to_string(), "2 N");
+        let options = DisplayOptions {
+            named_units: false,
+            ..Default::default()
+        };
+        assert_eq!(q.display(options).to_string(), "2 m·kg/s²");
+        let q = Quantity {
+            dimensions: Dimensions {
+                length: 2.into(),
+                ..q.dimensions
+            },
+            ..q
+        };
+        // joule is declared before electronvolt
+        assert_eq!(q.to_string(), "2 J");
+    }
+
+    #[test]
+    pub fn prefixes() {
+        let options = DisplayOptions {
+            prefixes: true,
+            ..Default::default()
+        };
+        let length = Dimensions {
+            length: 1.into(),
+            ..Default::default()
+        };
+        let q = Quantity::new(0.000012, length, Units::SI());
+        assert_eq!(q.display(options).to_string(), "12 μm");
+        let q = Quantity {
+            value: 0.002,
+            dimensions: Dimensions {
+                mass: 1.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(q.display(options).to_string(), "2 g");
+        let q = Quantity {
+            value: 0.5,
+            dimensions: Dimensions {
+                time: 1.into(),
+                ..Default::default()
+            },
+            units: Units {
+                time: time::Time::minute,
+                ..Units::SI()
+            },
+        };
+        // minutes take no prefixes
+        assert_eq!(q.display(options).to_string(), "0.5 min");
+    }
 }
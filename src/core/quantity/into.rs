@@ -3,9 +3,8 @@ use super::{Quantity, QuantityFloat};
 impl TryInto<QuantityFloat> for Quantity {
     type Error = String;
     fn try_into(self) -> Result<QuantityFloat, Self::Error> {
-        if self.dimensions.length == 0. && self.dimensions.mass == 0. && self.dimensions.time == 0.
-        {
-            Ok(self.value)
+        if self.dimensions.without_angle().is_dimensionless() {
+            Ok(self.with_angle(0.into()).value)
         } else {
             Err("Not a number".into())
         }
@@ -1,7 +1,8 @@
-use crate::core::DimensionError;
+use crate::core::level::Level;
+use crate::core::{DimensionError, Dimensions, Rational, Units};
 use std::ops::{Add, Div, Mul, Sub};
 
-use super::{Quantity, QuantityFloat};
+use super::{Quantity, QuantityError, QuantityFloat};
 
 impl Quantity {
     pub fn neg(&self) -> Self {
@@ -11,28 +12,88 @@ impl Quantity {
         }
     }
 
-    pub fn add(&self, r: &Self) -> Result<Self, DimensionError> {
-        if self.dimensions != r.dimensions {
-            Err(DimensionError(self.dimensions, r.dimensions))
+    pub fn add(&self, r: &Self) -> Result<Self, QuantityError> {
+        if self.units.level.is_some() && r.units.level.is_some() {
+            // adding levels multiplies the ratios they stand for
+            return self.combine_levels(r, self.mul(r));
+        }
+        if !self.dimensions.is_compatible(&r.dimensions) {
+            return Err(DimensionError(self.dimensions, r.dimensions).into());
+        }
+        // e.g. gray and sievert, which share their dimensions
+        let system = |q: &Self| q.units.derived.and_then(|u| Some((u.symbol, u.system?)));
+        if let (Some(l), Some(r)) = (system(self), system(r)) {
+            if l.1 != r.1 {
+                return Err(QuantityError::DerivedSystems(l, r));
+            }
+        }
+        let r = &r.with_angle(self.dimensions.angle);
+        match (self.is_absolute_temperature(), r.is_absolute_temperature()) {
+            (true, true) => Err(QuantityError::AbsoluteTemperatureSum),
+            // a difference added to a reading stays on the reading's scale
+            (false, true) => r.add(self),
+            _ => {
+                let r_converted = r.rescale(&self.units);
+                Ok(Self {
+                    value: self.value + r_converted.value,
+                    dimensions: self.dimensions,
+                    units: self.units,
+                })
+            }
+        }
+    }
+    pub fn sub(&self, r: &Self) -> Result<Self, QuantityError> {
+        if self.units.level.is_some() && r.units.level.is_some() {
+            self.combine_levels(r, self.div(r))
+        } else if self.is_absolute_temperature() && r.is_absolute_temperature() {
+            // two readings are a difference apart
+            let r_converted = r.set_units(&self.units);
+            let difference = Self {
+                value: self.value - r_converted.value,
+                ..*self
+            };
+            Ok(difference.rescale(&Units {
+                temperature: self.units.temperature.difference(),
+                ..self.units
+            }))
+        } else if self.dimensions.is_temperature() && r.is_absolute_temperature() {
+            Err(QuantityError::AbsoluteTemperatureSubtrahend)
         } else {
-            let r_converted = r.clone().set_units(&self.units.clone());
-            Ok(Self {
-                value: self.value + r_converted.value,
-                dimensions: self.dimensions,
-                units: self.units,
-            })
+            self.add(&r.neg())
         }
     }
-    pub fn sub(&self, r: &Self) -> Result<Self, DimensionError> {
-        self.add(&r.neg())
+
+    /// Shows the product or quotient `linear` of two levels as a gain, or as a level with a
+    /// reference if it has the dimensions of one, e.g. dBm + dB is in dBm but dBm + dBm is an error
+    fn combine_levels(&self, r: &Self, linear: Self) -> Result<Self, QuantityError> {
+        let levels = [self.units.level, r.units.level].map(|l| l.expect("not a level"));
+        let level = if linear.dimensions.is_dimensionless() {
+            levels
+                .into_iter()
+                .find(|l| !l.is_absolute())
+                .unwrap_or(Level::decibel)
+        } else {
+            levels
+                .into_iter()
+                .find(|l| l.is_absolute() && l.dimensions() == linear.dimensions)
+                .ok_or(QuantityError::Levels(levels[0], levels[1]))?
+        };
+        Ok(Self {
+            units: Units {
+                level: Some(level),
+                ..linear.units
+            },
+            ..linear
+        })
     }
 
     pub fn mul(&self, r: &Self) -> Self {
-        let r_converted = r.set_units(&self.units.clone());
+        let units = self.units.merge(&r.units, &self.dimensions);
+        let r_converted = r.set_units(&units);
         Self {
             value: self.value * r_converted.value,
             dimensions: self.dimensions.mul(&r.dimensions),
-            ..*self
+            units,
         }
     }
 
@@ -40,23 +101,42 @@ impl Quantity {
         self.mul(&r.inv())
     }
 
-    pub fn pow(&self, x: QuantityFloat) -> Self {
+    pub fn pow(&self, x: QuantityFloat) -> Result<Self, QuantityError> {
+        let dimensions = if self.dimensions.is_dimensionless() {
+            self.dimensions
+        } else {
+            let exp = Rational::approximate(x).ok_or(QuantityError::Exponent(x))?;
+            self.dimensions
+                .pow(exp)
+                .ok_or(QuantityError::ExponentOverflow(x))?
+        };
+        Ok(self.raise(x, dimensions))
+    }
+    pub fn inv(&self) -> Self {
+        self.raise(-1., self.dimensions.inv())
+    }
+    fn raise(&self, x: QuantityFloat, dimensions: Dimensions) -> Self {
         Self {
-            units: self.units,
-            dimensions: self.dimensions.pow(x),
+            units: Units {
+                // a power of a gain is still a gain, e.g. (3 dB)^2 is 6 dB
+                level: self
+                    .units
+                    .level
+                    .filter(|_| self.dimensions.is_dimensionless()),
+                derived: self.units.derived.filter(|_| x == 1.),
+                ..self.units
+            },
+            dimensions,
             value: self.value.powf(x),
         }
     }
-    pub fn inv(&self) -> Self {
-        self.clone().pow(-1.)
-    }
 }
 
 impl Add<Quantity> for Quantity {
     type Output = Quantity;
 
     fn add(self, rhs: Quantity) -> Self::Output {
-        Quantity::add(&self, &rhs).unwrap()
+        Quantity::add(&self, &rhs).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
@@ -64,7 +144,7 @@ impl Sub<Quantity> for Quantity {
     type Output = Quantity;
 
     fn sub(self, rhs: Quantity) -> Self::Output {
-        Quantity::sub(&self, &rhs).unwrap()
+        Quantity::sub(&self, &rhs).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
@@ -89,6 +169,7 @@ mod tests {
     use super::*;
     use crate::core::dimension::*;
     use crate::core::unit::length::Length::*;
+    use crate::core::unit::temperature::Temperature::{self, *};
     use crate::core::unit::Units;
 
     #[test]
@@ -96,7 +177,7 @@ mod tests {
         let m = Quantity {
             value: 2.,
             dimensions: Dimensions {
-                length: 2.,
+                length: 2.into(),
                 ..Default::default()
             },
             ..Default::default()
@@ -104,7 +185,7 @@ mod tests {
         let n = Quantity {
             value: -20.,
             dimensions: Dimensions {
-                length: 2.,
+                length: 2.into(),
                 ..Default::default()
             },
             units: Units {
@@ -117,7 +198,7 @@ mod tests {
             Quantity {
                 value: -19999998.,
                 dimensions: Dimensions {
-                    length: 2.,
+                    length: 2.into(),
                     ..Default::default()
                 },
                 ..Default::default()
@@ -131,7 +212,7 @@ mod tests {
         let m = Quantity {
             value: 30.,
             dimensions: Dimensions {
-                length: 2.,
+                length: 2.into(),
                 ..Default::default()
             },
             ..Default::default()
@@ -139,7 +220,7 @@ mod tests {
         let n = Quantity {
             value: 5.,
             dimensions: Dimensions {
-                length: 1.,
+                length: 1.into(),
                 ..Default::default()
             },
             ..Default::default()
@@ -151,7 +232,7 @@ mod tests {
             Quantity {
                 value: 6.,
                 dimensions: Dimensions {
-                    length: 1.,
+                    length: 1.into(),
                     ..Default::default()
                 },
                 ..Default::default()
@@ -165,7 +246,7 @@ mod tests {
         let m = Quantity {
             value: 5.,
             dimensions: Dimensions {
-                length: 2.,
+                length: 2.into(),
                 ..Default::default()
             },
             ..Default::default()
@@ -173,7 +254,7 @@ mod tests {
         let n = Quantity {
             value: 5.,
             dimensions: Dimensions {
-                length: 1.,
+                length: 1.into(),
                 ..Default::default()
             },
             ..Default::default()
@@ -185,7 +266,7 @@ mod tests {
             Quantity {
                 value: 25.,
                 dimensions: Dimensions {
-                    length: 3.,
+                    length: 3.into(),
                     ..Default::default()
                 },
                 ..Default::default()
@@ -193,4 +274,58 @@ mod tests {
             out
         );
     }
+
+    fn temperature(value: QuantityFloat, unit: Temperature) -> Quantity {
+        Quantity {
+            value,
+            dimensions: Dimensions {
+                temperature: 1.into(),
+                ..Default::default()
+            },
+            units: Units {
+                temperature: unit,
+                ..Units::SI()
+            },
+        }
+    }
+
+    #[test]
+    pub fn add_temperature_difference() {
+        let warmer = Quantity::add(
+            &temperature(20., celsius),
+            &temperature(9., delta_fahrenheit),
+        );
+        assert_eq!(Ok(temperature(25., celsius)), warmer);
+        let warmer = Quantity::add(&temperature(5., delta_kelvin), &temperature(20., celsius));
+        assert_eq!(Ok(temperature(25., celsius)), warmer);
+    }
+
+    #[test]
+    pub fn add_absolute_temperatures() {
+        assert_eq!(
+            Err(QuantityError::AbsoluteTemperatureSum),
+            Quantity::add(&temperature(20., celsius), &temperature(68., fahrenheit))
+        );
+        assert_eq!(
+            Err(QuantityError::AbsoluteTemperatureSum),
+            Quantity::add(&temperature(20., celsius), &temperature(300., kelvin))
+        );
+    }
+
+    #[test]
+    pub fn sub_absolute_temperatures() {
+        let diff =
+            Quantity::sub(&temperature(30., celsius), &temperature(50., fahrenheit)).unwrap();
+        assert_eq!(delta_celsius, diff.units.temperature);
+        assert!((diff.value - 20.).abs() < 1e-9);
+        let diff = Quantity::sub(&temperature(300., kelvin), &temperature(20., celsius)).unwrap();
+        assert_eq!(delta_kelvin, diff.units.temperature);
+        assert!((diff.value - 6.85).abs() < 1e-9);
+        let cooler = Quantity::sub(&temperature(30., celsius), &temperature(10., delta_celsius));
+        assert_eq!(Ok(temperature(20., celsius)), cooler);
+        assert_eq!(
+            Err(QuantityError::AbsoluteTemperatureSubtrahend),
+            Quantity::sub(&temperature(10., delta_celsius), &temperature(30., celsius))
+        );
+    }
 }
@@ -0,0 +1,251 @@
+use super::constant::CONSTANTS_LOOKUP;
+use super::level::LEVELS_LOOKUP;
+use super::quantity::{Quantity, QuantityFloat};
+use super::system::UNIT_SYSTEMS_LOOKUP;
+use super::unit::{DerivedUnit, Unit, Units, UNITS_LOOKUP};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+lazy_static! {
+    /// Units defined while running, e.g. with `unit smoot = 1.7018 m` in the REPL
+    static ref SESSION_UNITS: RwLock<HashMap<&'static str, Quantity>> =
+        RwLock::new(HashMap::new());
+    /// Prefixes for any unit, e.g. from an imported GNU units file
+    static ref SESSION_PREFIXES: RwLock<HashMap<&'static str, QuantityFloat>> =
+        RwLock::new(HashMap::new());
+}
+
+// words of the grammar, which cannot name a unit
+const KEYWORDS: &[&str] = &["ln", "log", "sqrt", "unit"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefinitionError {
+    InvalidName(String),
+    Taken(String),
+    Redefined(String),
+    ShiftedZero(String),
+}
+
+impl Display for DefinitionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidName(name) => write!(f, "`{}` is not a valid unit name", name),
+            Self::Taken(name) => write!(f, "`{}` already names something else", name),
+            Self::Redefined(name) => write!(f, "`{}` is already a unit of another size", name),
+            Self::ShiftedZero(name) => write!(
+                f,
+                "`{}` would lose the zero point of its temperature, define it in K or as a difference",
+                name
+            ),
+        }
+    }
+}
+
+/// House units loaded at startup, `units.txt` in the larvae config directory
+pub fn units_file() -> PathBuf {
+    super::config_dir().join("units.txt")
+}
+
+/// A built-in or session unit by name or symbol, or one of them with a session prefix
+pub fn unit(name: &str) -> Option<Quantity> {
+    exact(name).or_else(|| prefixed(name))
+}
+
+fn exact(name: &str) -> Option<Quantity> {
+    UNITS_LOOKUP.get(name).copied().or_else(|| {
+        SESSION_UNITS
+            .read()
+            .expect("unit registry poisoned")
+            .get(name)
+            .copied()
+    })
+}
+
+// the longest session prefix of `name` before a unit, in the units of that unit
+fn prefixed(name: &str) -> Option<Quantity> {
+    let (rest, factor) = SESSION_PREFIXES
+        .read()
+        .expect("unit registry poisoned")
+        .iter()
+        .filter_map(|(prefix, factor)| Some((name.strip_prefix(prefix)?, *factor)))
+        .filter(|(rest, _)| !rest.is_empty() && exact(rest).is_some())
+        .min_by_key(|(rest, _)| rest.len())?;
+    let unit = exact(rest)?;
+    Some(Quantity {
+        value: unit.value * factor,
+        ..unit
+    })
+}
+
+pub fn is_unit(name: &str) -> bool {
+    unit(name).is_some()
+}
+
+pub fn session_unit_names() -> Vec<&'static str> {
+    SESSION_UNITS
+        .read()
+        .expect("unit registry poisoned")
+        .keys()
+        .copied()
+        .collect()
+}
+
+/// Adds `name` as a unit of size `q`. Defining an existing unit again is allowed if the size is
+/// the same, so that a definition never silently changes what a name means.
+pub fn define(name: &str, q: &Quantity) -> Result<(), DefinitionError> {
+    if !is_valid(name) {
+        return Err(DefinitionError::InvalidName(name.to_string()));
+    }
+    // a unit only keeps a size, so a reading such as 20 °C cannot become one
+    if q.is_absolute_temperature() && q.units.temperature.offset() != 0. {
+        return Err(DefinitionError::ShiftedZero(name.to_string()));
+    }
+    let size = q.rescale(&Units::SI());
+    if let Some(existing) = exact(name) {
+        let existing = existing.rescale(&Units::SI());
+        let same = existing.dimensions == size.dimensions
+            && (existing.value - size.value).abs() <= size.value.abs() * 1e-12;
+        return same
+            .then_some(())
+            .ok_or_else(|| DefinitionError::Redefined(name.to_string()));
+    }
+    if is_taken(name) {
+        return Err(DefinitionError::Taken(name.to_string()));
+    }
+    // session units live as long as the program, like the built-in ones
+    let symbol: &'static str = Box::leak(name.to_string().into_boxed_str());
+    let derived = DerivedUnit {
+        symbol,
+        conversion_factor: size.value,
+        dimensions: size.dimensions,
+        system: q.units.derived.and_then(|u| u.system),
+    };
+    SESSION_UNITS
+        .write()
+        .expect("unit registry poisoned")
+        .insert(
+            symbol,
+            Quantity {
+                units: Units {
+                    derived: Some(derived),
+                    ..Units::SI()
+                },
+                ..size
+            },
+        );
+    Ok(())
+}
+
+/// Adds `name` as a prefix multiplying any unit by `factor`
+pub fn define_prefix(name: &str, factor: QuantityFloat) -> Result<(), DefinitionError> {
+    if !is_valid(name) {
+        return Err(DefinitionError::InvalidName(name.to_string()));
+    }
+    let mut prefixes = SESSION_PREFIXES.write().expect("unit registry poisoned");
+    match prefixes.get(name) {
+        Some(f) if (f - factor).abs() > factor.abs() * 1e-12 => {
+            Err(DefinitionError::Redefined(name.to_string()))
+        }
+        Some(_) => Ok(()),
+        None => {
+            prefixes.insert(Box::leak(name.to_string().into_boxed_str()), factor);
+            Ok(())
+        }
+    }
+}
+
+fn is_valid(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || !(c.is_ascii() || c.is_whitespace()))
+}
+
+fn is_taken(name: &str) -> bool {
+    CONSTANTS_LOOKUP.contains_key(name)
+        || LEVELS_LOOKUP.contains_key(name)
+        || UNIT_SYSTEMS_LOOKUP.contains_key(name)
+        || KEYWORDS.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::unit::temperature::Temperature;
+
+    #[test]
+    fn define_units() {
+        let furlong = UNITS_LOOKUP["furlong"];
+        assert_eq!(define("registry_furlong", &furlong), Ok(()));
+        assert_eq!(unit("registry_furlong").unwrap().value, 201.168);
+        assert!(session_unit_names().contains(&"registry_furlong"));
+        // the same size again is fine, another one is not
+        assert_eq!(define("furlong", &furlong), Ok(()));
+        assert_eq!(
+            define("furlong", &UNITS_LOOKUP["m"]),
+            Err(DefinitionError::Redefined("furlong".to_string()))
+        );
+    }
+
+    #[test]
+    fn prefixes() {
+        define("registry_smoot", &UNITS_LOOKUP["m"]).unwrap();
+        define_prefix("registry_kilo", 1e3).unwrap();
+        assert_eq!(define_prefix("registry_kilo", 1e3), Ok(()));
+        assert_eq!(unit("registry_kiloregistry_smoot").unwrap().value, 1e3);
+        assert_eq!(unit("registry_kilom").unwrap().value, 1e3);
+        // looking a prefixed unit up does not define it
+        assert!(!session_unit_names().contains(&"registry_kilom"));
+        assert_eq!(unit("registry_kilo"), None);
+        assert_eq!(
+            define_prefix("registry_kilo", 1e6),
+            Err(DefinitionError::Redefined("registry_kilo".to_string()))
+        );
+    }
+
+    #[test]
+    fn shifted_temperatures() {
+        let reading = |t| Quantity {
+            value: 20.,
+            units: Units {
+                temperature: t,
+                ..Units::SI()
+            },
+            ..UNITS_LOOKUP["K"]
+        };
+        assert_eq!(
+            define("registry_warm", &reading(Temperature::celsius)),
+            Err(DefinitionError::ShiftedZero("registry_warm".to_string()))
+        );
+        assert_eq!(
+            define("registry_step", &reading(Temperature::delta_celsius)),
+            Ok(())
+        );
+        assert_eq!(unit("registry_step").unwrap().value, 20.);
+    }
+
+    #[test]
+    fn invalid_definitions() {
+        let m = UNITS_LOOKUP["m"];
+        assert_eq!(
+            define("2m", &m),
+            Err(DefinitionError::InvalidName("2m".to_string()))
+        );
+        assert_eq!(
+            define("a+b", &m),
+            Err(DefinitionError::InvalidName("a+b".to_string()))
+        );
+        assert_eq!(
+            define("pi", &m),
+            Err(DefinitionError::Taken("pi".to_string()))
+        );
+        assert_eq!(
+            define("cgs", &m),
+            Err(DefinitionError::Taken("cgs".to_string()))
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use super::unit::Units;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+/// Coherent sets of base units to express results in, as with `-> cgs`
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitSystem {
+    SI,
+    CGS,
+    MTS,
+    imperial,
+    atomic,
+    planck,
+}
+
+pub const UNIT_SYSTEMS: &[UnitSystem] = &[
+    UnitSystem::SI,
+    UnitSystem::CGS,
+    UnitSystem::MTS,
+    UnitSystem::imperial,
+    UnitSystem::atomic,
+    UnitSystem::planck,
+];
+
+lazy_static! {
+    pub static ref UNIT_SYSTEMS_LOOKUP: HashMap<&'static str, UnitSystem> = UNIT_SYSTEMS
+        .iter()
+        .flat_map(|s| [(s.name(), *s), (s.abbrev(), *s)])
+        .collect();
+}
+
+impl UnitSystem {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SI => "si",
+            Self::CGS => "cgs",
+            Self::MTS => "mts",
+            Self::imperial => "imperial",
+            Self::atomic => "atomic",
+            Self::planck => "planck",
+        }
+    }
+    pub fn abbrev(&self) -> &'static str {
+        match self {
+            Self::SI => "SI",
+            Self::CGS => "CGS",
+            Self::MTS => "MTS",
+            _ => self.name(),
+        }
+    }
+    pub fn units(&self) -> Units {
+        match self {
+            // unlike `Units::SI()`, shown in base units rather than e.g. J
+            Self::SI => Units {
+                system: Some(Self::SI),
+                ..Units::SI()
+            },
+            Self::CGS => Units::CGS(),
+            Self::MTS => Units::MTS(),
+            Self::imperial => Units::imperial(),
+            Self::atomic => Units::atomic(),
+            Self::planck => Units::planck(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup() {
+        for system in UNIT_SYSTEMS {
+            assert_eq!(UNIT_SYSTEMS_LOOKUP[system.name()], *system);
+            assert_eq!(system.units().system, Some(*system));
+        }
+        assert_eq!(UNIT_SYSTEMS_LOOKUP["CGS"], UnitSystem::CGS);
+    }
+}
@@ -1,26 +1,224 @@
-use super::dimension::Dimensions;
+use super::currency;
+use super::dimension::{Dimensions, Rational};
+use super::level::Level;
 use super::quantity::Quantity;
 use super::quantity::QuantityFloat;
+use super::system::UnitSystem;
 use larvae_macros::dimensions;
 use lazy_static::lazy_static;
 use std::collections::HashMap;
+use std::f64::consts::{PI, TAU};
 use std::fmt::Display;
 
+// each system is declared with the exponents of the base dimensions, or a formula of units
+// declared before it as in `"energy" = "N m"`. Units are sized with a factor or, in SI units,
+// with a formula such as `= "lbf / in^2"`. A name, abbrev or symbol shared by two units, also
+// when spelled by a prefix, does not compile unless one of them is marked `#[prefer]`, nor does
+// one shared with a constant unless the unit is, to mean the unit after a number as in `2 h`.
+// Prefixed units are never preferred, `#[no_prefix(h)]` leaves out those that collide.
+// results are shown in the first unit of the only derived system with their dimensions, if
+// that unit is coherent, so that m/s has no velocity unit and J/kg is neither Gy nor Sv
 dimensions! {
+    #![constants = "src/core/constant.rs"]
     "length" 1. 0. 0. :
-    "meter" "m" "m" 1.!,
+    "meter" "m" "m" 1.!
+    "inch" "in" "″" 0.0254
+    "foot" "ft" "′" 0.3048
+    "yard" "yd" "yd" 0.9144
+    "mile" "mi" "mi" 1609.344
+    "nautical_mile" "nmi" "nmi" 1852.
+    "thou" "mil" "mil" 0.0000254
+    "furlong" "fur" "fur" 201.168
+    "chain" "ch" "ch" 20.1168
+    "fathom" "ftm" "ftm" 1.8288
+    "planck_length" "l_P" "l_P" 1.616255e-35
+    "astronomical_unit" "au" "au" 149597870700.
+    "light_year" "ly" "ly" 9460730472580800.
+    "parsec" "pc" "pc" (149597870700. * 648000. / std::f64::consts::PI)![multiples]
+    "solar_radius" "R_sun" "R☉" 6.957e8
+    // typed as the angstrom sign U+212B, shown as the letter U+00C5
+    "angstrom" "Å" "Å" 1e-10
+    "fermi" "fermi" "fermi" 1e-15
+    #[prefer] "bohr_radius" "a0" "a₀" 5.29177210903e-11,
     "mass" 0. 1. 0. :
-    "gram" "g" "g" 1.!,
+    "gram" "g" "g" 1.!
+    "tonne" "t" "t" 1e6![multiples]
+    "pound" "lb" "lb" 453.59237
+    "ounce" "oz" "oz" 28.349523125
+    "dram" "dr" "dr" 1.7718451953125
+    "grain" "gr" "gr" 0.06479891
+    "stone" "st" "st" 6350.29318
+    "short_ton" "ton" "ton" 907184.74
+    "long_ton" "LT" "LT" 1016046.9088
+    "troy_ounce" "ozt" "ozt" 31.1034768
+    "troy_pound" "lbt" "lbt" 373.2417216
+    "pennyweight" "dwt" "dwt" 1.55517384
+    "scruple" "scr" "℈" 1.2959782
+    "apothecary_dram" "drap" "ʒ" 3.8879346
+    #[prefer] "electron_mass" "m_e" "mₑ" 9.1093837015e-28
+    "planck_mass" "m_P" "m_P" (1.054571817e-31 / (1.616255e-35 * 299792458.))
+    "solar_mass" "M_sun" "M☉" 1.98847e33
+    "earth_mass" "M_earth" "M⊕" 5.9722e27
+    "jupiter_mass" "M_jup" "M♃" 1.89813e30
+    "dalton" "Da" "Da" 1.66053906660e-24![multiples]
+    "atomic_mass_unit" "amu" "amu" 1.66053906660e-24,
     "time" 0. 0. 1. :
     "second" "s" "s" 1.!
     "minute" "min" "min" 60.
-    "hour" "h" "hr" 3600.,
-    "force" 1. 1. -2. :
-    "newton" "N" "N" 1.!,
+    #[prefer] "hour" "h" "hr" 3600.
+    "day" "d" "d" 86400.
+    "week" "wk" "wk" 604800.
+    "fortnight" "fortnight" "fortnight" 1209600.
+    "sidereal_day" "d_sid" "d_sid" 86164.0905
+    "julian_year" "yr" "yr" 31557600.![multiples]
+    "gregorian_year" "yr_g" "yr_g" 31556952.
+    "tropical_year" "yr_t" "yr_t" 31556925.216
+    "atomic_unit_of_time" "t_au" "t_au" 2.4188843265857e-17
+    "planck_time" "t_P" "t_P" (1.616255e-35 / 299792458.),
+    "temperature" 0. 0. 0. 1. :
+    "kelvin" "K" "K" 1.!
+    "celsius" "degC" "°C" 1. + 273.15
+    "fahrenheit" "degF" "°F" (5. / 9.) + 459.67
+    "rankine" "degR" "°R" (5. / 9.)
+    "delta_celsius" "deltaC" "ΔC" 1.
+    "delta_fahrenheit" "deltaF" "ΔF" (5. / 9.)
+    "delta_kelvin" "deltaK" "ΔK" 1.
+    "delta_rankine" "deltaR" "ΔR" (5. / 9.)
+    "planck_temperature" "T_P" "T_P" 1.416784e32,
+    "amount" 0. 0. 0. 0. 1. :
+    "mole" "mol" "mol" 1.!,
+    "luminous_intensity" 0. 0. 0. 0. 0. 1. :
+    "candela" "cd" "cd" 1.!,
+    "angle" 0. 0. 0. 0. 0. 0. 1. :
+    "radian" "rad" "rad" 1.!
+    "degree" "deg" "°" (PI / 180.)
+    "arcminute" "arcmin" "arcmin" (PI / 180. / 60.)
+    "arcsecond" "arcsec" "arcsec" (PI / 180. / 3600.)
+    "turn" "rev" "turn" (TAU)
+    "gradian" "grad" "gon" (PI / 200.),
+    "solid_angle" = "rad^2" :
+    "steradian" "sr" "sr" 1.,
+    "information" 0. 0. 0. 0. 0. 0. 0. 1. :
+    "bit" "bit" "b" 1.![multiples, binary]
+    "byte" "B" "B" 8.![multiples, binary],
+    "money" 0. 0. 0. 0. 0. 0. 0. 0. 1. :
+    "us_dollar" "USD" "$" (currency::value_of("USD"))
+    "euro" "EUR" "€" (currency::value_of("EUR"))
+    "pound_sterling" "GBP" "£" (currency::value_of("GBP"))
+    "yen" "JPY" "¥" (currency::value_of("JPY"))
+    "swiss_franc" "CHF" "CHF" (currency::value_of("CHF"))
+    "yuan" "CNY" "CNY" (currency::value_of("CNY"))
+    "canadian_dollar" "CAD" "CAD" (currency::value_of("CAD"))
+    "australian_dollar" "AUD" "AUD" (currency::value_of("AUD"))
+    "new_zealand_dollar" "NZD" "NZD" (currency::value_of("NZD"))
+    "swedish_krona" "SEK" "SEK" (currency::value_of("SEK"))
+    "norwegian_krone" "NOK" "NOK" (currency::value_of("NOK"))
+    "danish_krone" "DKK" "DKK" (currency::value_of("DKK"))
+    "indian_rupee" "INR" "₹" (currency::value_of("INR"))
+    "brazilian_real" "BRL" "BRL" (currency::value_of("BRL"))
+    "mexican_peso" "MXN" "MXN" (currency::value_of("MXN"))
+    "won" "KRW" "₩" (currency::value_of("KRW"))
+    "hong_kong_dollar" "HKD" "HKD" (currency::value_of("HKD"))
+    "singapore_dollar" "SGD" "SGD" (currency::value_of("SGD"))
+    "rand" "ZAR" "ZAR" (currency::value_of("ZAR"))
+    "zloty" "PLN" "PLN" (currency::value_of("PLN"))
+    "czech_koruna" "CZK" "CZK" (currency::value_of("CZK")),
+    "current" 0. 0. 0. 0. 0. 0. 0. 0. 0. 1. :
+    "ampere" "A" "A" 1.!
+    "atomic_unit_of_current" "I_au" "I_au" 6.623618237510e-3
+    "planck_current" "I_P" "I_P" 3.478873e25,
+    "charge" = "A s" :
+    "coulomb" "C" "C" 1.!
+    "ampere_hour" "Ah" "Ah" = "A h"!,
+    "voltage" = "kg m^2 / A s^3" :
+    "volt" "V" "V" 1.!,
+    "resistance" = "V / A" :
+    "ohm" "ohm" "Ω" 1.!,
+    "conductance" = "A / V" :
+    "siemens" "S" "S" 1.!,
+    "capacitance" = "C / V" :
+    #[prefer] "farad" "F" "F" 1.!,
+    "inductance" = "V s / A" :
+    "henry" "H" "H" 1.!,
+    "magnetic_flux" = "V s" :
+    "weber" "Wb" "Wb" 1.!,
+    "magnetic_flux_density" = "Wb / m^2" :
+    "tesla" "T" "T" 1.!
+    // `G` is the gravitational constant, and `Gs` is read as gauss rather than gigasecond
+    #[prefer] "gauss" "Gs" "Gs" 1e-4!,
+    "area" = "m^2" :
+    "are" "a" "a" 100.
+    "hectare" "ha" "ha" 1e4
+    "acre" "ac" "ac" 4046.8564224
+    "barn" "barn" "barn" 1e-28,
+    "volume" = "m^3" :
+    "litre" "l" "L" 1e-3!
+    "gallon" "gal" "gal" = "231 in^3"
+    "quart" "qt" "qt" 9.46352946e-4
+    "pint" "pt" "pt" 4.73176473e-4
+    "cup" "cup" "cup" 2.365882365e-4
+    "fluid_ounce" "floz" "floz" 2.95735295625e-5
+    "tablespoon" "tbsp" "tbsp" 1.478676478125e-5
+    "teaspoon" "tsp" "tsp" 4.92892159375e-6
+    "imperial_gallon" "impgal" "impgal" 4.54609e-3
+    "imperial_quart" "impqt" "impqt" 1.1365225e-3
+    "imperial_pint" "imppt" "imppt" 5.6826125e-4
+    "imperial_fluid_ounce" "impfloz" "impfloz" 2.84130625e-5
+    "oil_barrel" "bbl" "bbl" 0.158987294928,
+    "frequency" = "1 / s" :
+    "hertz" "Hz" "Hz" 1.!
+    "revolutions_per_minute" "rpm" "rpm" = "1 / min"
+    "beats_per_minute" "bpm" "bpm" = "1 / min",
+    "velocity" = "m / s" :
+    "knot" "kn" "kn" = "nmi / h"
+    "mile_per_hour" "mph" "mph" = "mi / h"
+    "kilometre_per_hour" "kph" "kph" = "km / h"
+    "foot_per_second" "fps" "fps" = "ft / s"
+    #[prefer] "speed_of_light" "c" "c" 299792458.
+    // speed of sound in the ISA at sea level
+    "mach" "mach" "Ma" 340.294,
+    "acceleration" = "m / s^2" :
+    #[prefer] "gee" "g0" "g₀" 9.80665,
+    "force" = "kg m / s^2" :
+    "newton" "N" "N" 1.!
+    "pound_force" "lbf" "lbf" = "lb g0",
+    "power" = "N m / s" :
+    "watt" "W" "W" 1.!
+    "horsepower" "hp" "hp" = "550 ft lbf / s"
+    // rather than a petasiemens
+    #[prefer] "metric_horsepower" "PS" "PS" = "75 kg g0 m / s",
+    "energy" = "N m" :
+    "joule" "J" "J" 1.!
+    "electronvolt" "eV" "eV" 1.602176634e-19!
+    "watt_hour" "Wh" "Wh" = "W h"!
+    "calorie" "cal" "cal" 4.184![multiples]
+    "british_thermal_unit" "BTU" "BTU" 1055.05585262
+    "erg" "erg" "erg" = "g cm^2 / s^2",
+    "pressure" = "N / m^2" :
+    "pascal" "Pa" "Pa" 1.!
+    #[no_prefix(h)] "bar" "bar" "bar" 1e5!
+    "atmosphere" "atm" "atm" 101325.
+    "psi" "psi" "psi" = "lbf / in^2"
+    "torr" "Torr" "Torr" = "atm / 760"
+    "millimeter_of_mercury" "mmHg" "mmHg" 133.322387415,
+    "activity" = "1 / s" :
+    "becquerel" "Bq" "Bq" 1.!
+    "curie" "Ci" "Ci" 3.7e10!,
+    "absorbed_dose" = "J / kg" :
+    "gray" "Gy" "Gy" 1.!
+    // `rad` is the radian
+    "rad_dose" "rd" "rd" 1e-2!,
+    "equivalent_dose" = "J / kg" :
+    "sievert" "Sv" "Sv" 1.!
+    "rem" "rem" "rem" 1e-2!,
+    "exposure" = "C / kg" :
+    // `R` is the gas constant
+    "roentgen" "roentgen" "R_exp" 2.58e-4!,
 }
 
 pub trait Unit {
     fn conversion_factor(&self) -> QuantityFloat;
+    fn offset(&self) -> QuantityFloat;
     fn abbrev(&self) -> &'static str;
     fn name(&self) -> &'static str;
     fn symbol(&self) -> &'static str;
@@ -32,13 +230,77 @@ impl std::fmt::Display for dyn Unit {
         write!(fmt, "{}", self.symbol())
     }
 }
-pub trait CompoundUnit: Unit {}
+
+impl Temperature {
+    /// Scales on which values are readings, as opposed to differences of readings
+    pub fn is_absolute(&self) -> bool {
+        !self.is_difference()
+    }
+    pub fn is_difference(&self) -> bool {
+        matches!(
+            self,
+            Self::delta_kelvin | Self::delta_celsius | Self::delta_fahrenheit | Self::delta_rankine
+        )
+    }
+    /// The unit for differences of temperatures measured in `self`
+    pub fn difference(&self) -> Self {
+        match self {
+            Self::kelvin | Self::planck_temperature => Self::delta_kelvin,
+            Self::celsius => Self::delta_celsius,
+            Self::fahrenheit => Self::delta_fahrenheit,
+            Self::rankine => Self::delta_rankine,
+            _ => *self,
+        }
+    }
+    /// The scale of the same size as `self` that starts at absolute zero
+    pub fn thermodynamic(&self) -> Self {
+        match self {
+            Self::celsius => Self::kelvin,
+            Self::fahrenheit => Self::rankine,
+            _ => *self,
+        }
+    }
+}
+
+/// A unit of a derived system, which values are not stored in but can be shown in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DerivedUnit {
+    pub symbol: &'static str,
+    // size in SI units
+    pub conversion_factor: QuantityFloat,
+    pub dimensions: Dimensions,
+    // the derived system, as gray and sievert are not interchangeable despite their dimensions
+    pub system: Option<&'static str>,
+}
+impl DerivedUnit {
+    pub fn new(unit: &dyn Unit, system: &'static str) -> Self {
+        Self {
+            symbol: unit.symbol(),
+            conversion_factor: unit.conversion_factor(),
+            dimensions: unit.dimensions(),
+            system: Some(system),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Units {
     pub length: Length,
     pub mass: Mass,
     pub time: Time,
+    pub temperature: Temperature,
+    pub amount: Amount,
+    pub luminous_intensity: LuminousIntensity,
+    pub angle: Angle,
+    pub information: Information,
+    pub money: Money,
+    pub current: Current,
+    // shown on a logarithmic scale
+    pub level: Option<Level>,
+    // shown in a unit of a derived system, e.g. kN
+    pub derived: Option<DerivedUnit>,
+    // shown in the base units of a unit system, e.g. cm·g/s² rather than N
+    pub system: Option<UnitSystem>,
 }
 impl Display for Units {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -46,12 +308,96 @@ impl Display for Units {
     }
 }
 impl Units {
+    /// Keeps the units of the dimensions in `dims` and takes the rest from `r`
+    pub fn merge(&self, r: &Self, dims: &Dimensions) -> Self {
+        macro_rules! pick {
+            ($($dim:ident),+) => {
+                Self {
+                    $($dim: if dims.$dim == Rational::ZERO { r.$dim } else { self.$dim }),+,
+                    level: None,
+                    derived: None,
+                    system: None,
+                }
+            };
+        }
+        pick!(
+            length,
+            mass,
+            time,
+            temperature,
+            amount,
+            luminous_intensity,
+            angle,
+            information,
+            money,
+            current
+        )
+    }
     #[allow(non_snake_case)]
     pub const fn SI() -> Self {
         Self {
             length: Length::meter,
             mass: Mass::kilogram,
             time: Time::second,
+            temperature: Temperature::kelvin,
+            amount: Amount::mole,
+            luminous_intensity: LuminousIntensity::candela,
+            angle: Angle::radian,
+            information: Information::bit,
+            money: Money::us_dollar,
+            current: Current::ampere,
+            level: None,
+            derived: None,
+            system: None,
+        }
+    }
+    #[allow(non_snake_case)]
+    pub const fn CGS() -> Self {
+        Self {
+            length: Length::centimeter,
+            mass: Mass::gram,
+            system: Some(UnitSystem::CGS),
+            ..Self::SI()
+        }
+    }
+    /// Metre-tonne-second
+    #[allow(non_snake_case)]
+    pub const fn MTS() -> Self {
+        Self {
+            mass: Mass::tonne,
+            system: Some(UnitSystem::MTS),
+            ..Self::SI()
+        }
+    }
+    pub const fn imperial() -> Self {
+        Self {
+            length: Length::foot,
+            mass: Mass::pound,
+            temperature: Temperature::fahrenheit,
+            system: Some(UnitSystem::imperial),
+            ..Self::SI()
+        }
+    }
+    /// Hartree atomic units
+    pub const fn atomic() -> Self {
+        Self {
+            length: Length::bohr_radius,
+            mass: Mass::electron_mass,
+            time: Time::atomic_unit_of_time,
+            current: Current::atomic_unit_of_current,
+            system: Some(UnitSystem::atomic),
+            ..Self::SI()
+        }
+    }
+    pub const fn planck() -> Self {
+        Self {
+            length: Length::planck_length,
+            mass: Mass::planck_mass,
+            time: Time::planck_time,
+            temperature: Temperature::planck_temperature,
+            current: Current::planck_current,
+            system: Some(UnitSystem::planck),
+            ..Self::SI()
         }
     }
 }
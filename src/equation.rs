@@ -1,21 +1,60 @@
 mod parser;
 mod semanter;
 mod tokenizer;
-pub use parser::parser;
+pub use parser::{constant_parser, parser};
 pub use semanter::semanter;
 pub use tokenizer::tokenizer;
 
+use crate::core::Quantity;
+use earlgrey::{EarleyForest, EarleyParser};
+use std::panic::{self, AssertUnwindSafe};
+
+/// Evaluates `input` as read by the first of `parsers` that parses and evaluates it, so that
+/// `1 km / h` is a speed but `1 eV / h -> Hz` divides by the planck constant. Evaluation errors
+/// are panics, of the first reading that parses if none evaluates
+pub fn evaluate(
+    parsers: &[EarleyParser],
+    semanter: &EarleyForest<Quantity>,
+    input: &str,
+) -> Result<Quantity, String> {
+    let mut parse_error = None;
+    let mut eval_error = None;
+    for parser in parsers {
+        let state = match parser.parse(tokenizer(input.chars())) {
+            Ok(state) => state,
+            Err(e) => {
+                parse_error.get_or_insert(e);
+                continue;
+            }
+        };
+        match panic::catch_unwind(AssertUnwindSafe(|| semanter.eval(&state))) {
+            Ok(Ok(q)) => return Ok(q),
+            Ok(Err(e)) => {
+                parse_error.get_or_insert(e);
+            }
+            Err(panic) => {
+                eval_error.get_or_insert(panic);
+            }
+        }
+    }
+    if let Some(panic) = eval_error {
+        panic::resume_unwind(panic);
+    }
+    Err(parse_error.expect("no parsers"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::core::quantity::*;
+    use crate::core::registry;
+    use crate::core::unit::amount::Amount::*;
     use crate::core::unit::length::Length::*;
-    use crate::core::{dimension::Dimensions, unit::Units};
+    use crate::core::unit::temperature::Temperature::*;
+    use crate::core::{dimension::Dimensions, dimension::Rational, unit::Units};
 
     fn calc(input: &str) -> Result<Quantity, String> {
-        let tokens = tokenizer(input.chars());
-        let state = parser().parse(tokens)?;
-        semanter().eval(&state)
+        evaluate(&[parser(), constant_parser()], &semanter(None), input)
     }
     #[test]
     fn one_equals_one() {
@@ -28,7 +67,7 @@ mod tests {
             Ok(Quantity {
                 value: 1e-3,
                 dimensions: Dimensions {
-                    length: 1.,
+                    length: 1.into(),
                     ..Default::default()
                 },
                 units: Units {
@@ -46,8 +85,8 @@ mod tests {
             Ok(Quantity {
                 value: 1.,
                 dimensions: Dimensions {
-                    length: 1.,
-                    time: -2.,
+                    length: 1.into(),
+                    time: (-2).into(),
                     ..Default::default()
                 },
                 units: Units {
@@ -57,4 +96,398 @@ mod tests {
             calc("1m/s^2")
         );
     }
+
+    #[test]
+    fn thermal_chemistry_units() {
+        assert_eq!(
+            Ok(Quantity {
+                value: 2.,
+                dimensions: Dimensions {
+                    amount: 1.into(),
+                    temperature: (-1).into(),
+                    ..Default::default()
+                },
+                units: Units {
+                    amount: millimole,
+                    ..Default::default()
+                },
+            }),
+            calc("2 mmol/K")
+        );
+        assert_eq!(
+            Ok(Quantity {
+                value: 5e3,
+                dimensions: Dimensions {
+                    temperature: 1.into(),
+                    ..Default::default()
+                },
+                units: Units {
+                    temperature: millikelvin,
+                    ..Default::default()
+                },
+            }),
+            calc("5 K -> mK")
+        );
+    }
+
+    #[test]
+    fn fractional_powers() {
+        let cube_root = calc("(8 m^(1/3))^3 + 1 m");
+        assert_eq!(
+            Ok(Quantity {
+                value: 513.,
+                dimensions: Dimensions {
+                    length: 1.into(),
+                    ..Default::default()
+                },
+                units: Units::default(),
+            }),
+            cube_root
+        );
+        assert_eq!(
+            calc("4 m^(1/3)").unwrap().to_string(),
+            "4 m^(1/3)".to_string()
+        );
+        assert_eq!(
+            calc("1 kg^(-1/2) s").unwrap().dimensions,
+            Dimensions {
+                mass: Rational::new(-1, 2),
+                time: 1.into(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot raise a quantity with dimensions to the power 1.41421")]
+    fn irrational_power() {
+        let _ = calc("(1 m)^sqrt 2");
+    }
+
+    #[test]
+    #[should_panic(expected = "dimension exponents out of range in the power 3")]
+    fn power_overflow() {
+        let _ = calc("(((1 m)^30000)^30000)^3");
+    }
+
+    #[test]
+    fn temperature_scales() {
+        assert_eq!(calc("20 °C -> °F").unwrap().to_string(), "68 °F");
+        assert_eq!(calc("20 degC -> K").unwrap().to_string(), "293.15 K");
+        assert_eq!(calc("10 ΔC -> ΔF").unwrap().to_string(), "18 ΔF");
+        assert_eq!(calc("25 °C - 20 °C").unwrap().to_string(), "5 ΔC");
+        assert_eq!(calc("20 °C + 5 ΔC -> °F").unwrap().to_string(), "77 °F");
+        assert_eq!(calc("20 °C - 300 K").unwrap().to_string(), "-6.85 ΔC");
+        assert_eq!(calc("300 K - 20 °C").unwrap().to_string(), "6.85 ΔK");
+        assert_eq!(calc("2 * 293.15 K").unwrap().to_string(), "586.3 K");
+        assert_eq!(calc("1 kg/°C -> kg/K").unwrap().to_string(), "1 kg/K");
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot multiply a reading in °C, convert it to K first")]
+    fn shifted_scale_product() {
+        let _ = calc("2 * 20 °C");
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot convert between absolute temperatures and temperature")]
+    fn absolute_temperature_to_difference() {
+        let _ = calc("20 °C -> ΔF");
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot add two absolute temperatures")]
+    fn absolute_temperature_sum() {
+        let _ = calc("20 °C + 300 K");
+    }
+
+    #[test]
+    fn angles() {
+        assert_eq!(calc("90 deg -> rad").unwrap().to_string(), "1.57079633 rad");
+        assert_eq!(calc("3.141592653589793 -> °").unwrap().to_string(), "180 °");
+        assert_eq!(calc("1 turn -> grad").unwrap().to_string(), "400 gon");
+        assert_eq!(calc("90 deg + 30 arcmin").unwrap().to_string(), "90.5 °");
+        assert_eq!(
+            calc("2 rad/s -> rev/min").unwrap().to_string(),
+            "19.0985932 turn/min"
+        );
+        assert_eq!(Ok(0.5), calc("1 rad / 2").and_then(|q| q.try_into()));
+    }
+
+    #[test]
+    fn conversion_units_merge() {
+        assert_eq!(calc("1 m/s -> km/h").unwrap().to_string(), "3.6 km/hr");
+    }
+
+    #[test]
+    #[should_panic(expected = "incompatible dimensions length and time")]
+    fn incompatible_conversion() {
+        let _ = calc("1 m -> s");
+    }
+
+    #[test]
+    fn information() {
+        assert_eq!(
+            calc("1 GiB / (100 Mbit/s) -> s").unwrap().to_string(),
+            "85.8993459 s"
+        );
+        assert_eq!(calc("1 KiB -> bit").unwrap().to_string(), "8192 b");
+        assert_eq!(calc("2 kibibyte -> byte").unwrap().to_string(), "2048 B");
+        assert_eq!(calc("1 TB -> GB").unwrap().to_string(), "1000 GB");
+    }
+
+    #[test]
+    fn money() {
+        // a single currency needs no exchange rates
+        assert_eq!(calc("2 EUR * 3").unwrap().to_string(), "6 €");
+        assert_eq!(calc("0.5 USD/kg * 3 kg").unwrap().to_string(), "1.5 $");
+        assert_eq!(calc("10 GBP + 5 GBP").unwrap().to_string(), "15 £");
+    }
+
+    #[test]
+    fn prefixed_derived_units() {
+        assert_eq!(calc("5 kN").unwrap().to_string(), "5 kN");
+        assert_eq!(calc("5 kN + 20 N").unwrap().to_string(), "5.02 kN");
+    }
+
+    #[test]
+    fn levels() {
+        assert_eq!(calc("30 dBm -> kg m^2/s^3").unwrap().to_string(), "1 W");
+        assert_eq!(
+            calc("2000 kg m^2/s^3 -> dBm").unwrap().to_string(),
+            "63.0103 dBm"
+        );
+        assert_eq!(calc("30 dBm -> W").unwrap().to_string(), "1 W");
+        assert_eq!(calc("2 kW -> dBm").unwrap().to_string(), "63.0103 dBm");
+        assert_eq!(calc("3 dB + 3 dB").unwrap().to_string(), "6 dB");
+        assert_eq!(calc("30 dBm + 10 dB -> dBW").unwrap().to_string(), "10 dBW");
+        assert_eq!(calc("30 dBm - 20 dBm").unwrap().to_string(), "10 dB");
+        assert_eq!(calc("20 dB -> bel").unwrap().to_string(), "2 bel");
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot combine levels in dBm and dBW")]
+    fn absolute_level_sum() {
+        let _ = calc("30 dBm + 0 dBW");
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(calc("h*c/(500 nm)").unwrap().to_string(), "3.9729e-19 J");
+        assert_eq!(
+            calc("h*c/(500 nm) -> eV").unwrap().to_string(),
+            "2.47968397 eV"
+        );
+        assert_eq!(calc("2 * pi").unwrap(), (2. * std::f64::consts::PI).into());
+        assert_eq!(calc("e^2").unwrap(), std::f64::consts::E.powi(2).into());
+        assert_eq!(
+            calc("k_B * N_A").unwrap().dimensions,
+            calc("R").unwrap().dimensions
+        );
+        // after a number `h` is the hour, after `*` the planck constant
+        assert_eq!(calc("1 km / h").unwrap().to_string(), "1 km/hr");
+        assert_eq!(calc("2*h").unwrap(), calc("2 * planck_constant").unwrap());
+        assert_eq!(calc("1 kg * h").unwrap(), calc("h * 1 kg").unwrap());
+        assert_eq!(
+            calc("1 mol * F").unwrap(),
+            calc("1 mol * faraday_constant").unwrap()
+        );
+        assert_eq!(calc("c*h").unwrap(), calc("h*c").unwrap());
+        assert_eq!(calc("G*1M_sun").unwrap().to_string(), "1.32716e20 m³/s²");
+        // and after `/` too, when the hour would not convert
+        assert_eq!(
+            calc("1 kg m^2/s^2 / h -> s^-1").unwrap().to_string(),
+            "1.50919e33 1/s"
+        );
+        assert_eq!(calc("1 eV / h -> Hz").unwrap().to_string(), "2.41799e14 Hz");
+        assert_eq!(
+            calc("1 eV / hbar").unwrap(),
+            calc("1 eV / reduced_planck_constant").unwrap()
+        );
+    }
+
+    #[test]
+    fn named_derived_units() {
+        assert_eq!(calc("1 kg * 1 m / s^2").unwrap().to_string(), "1 N");
+        assert_eq!(calc("2 W * 1 min").unwrap().to_string(), "120 J");
+    }
+
+    #[test]
+    fn us_customary_length() {
+        assert_eq!(calc("3in -> mm").unwrap().to_string(), "76.2 mm");
+        assert_eq!(calc("12 in -> ft").unwrap().to_string(), "1 ′");
+        assert_eq!(calc("6′ + 2″ -> cm").unwrap().to_string(), "187.96 cm");
+        assert_eq!(calc("3 in + 1′").unwrap().to_string(), "15 ″");
+        assert_eq!(calc("1 mi -> km").unwrap().to_string(), "1.609344 km");
+        assert_eq!(calc("1 furlong -> ch").unwrap().to_string(), "10 ch");
+        assert_eq!(calc("1 nmi -> m").unwrap().to_string(), "1852 m");
+    }
+
+    #[test]
+    fn imperial_mass() {
+        assert_eq!(calc("150 lb -> kg").unwrap().to_string(), "68.0388555 kg");
+        assert_eq!(calc("1 t -> lb").unwrap().to_string(), "2204.62262 lb");
+        assert_eq!(calc("14 lb -> st").unwrap().to_string(), "1 st");
+        assert_eq!(calc("1 LT -> lb").unwrap().to_string(), "2240 lb");
+        assert_eq!(calc("1 ozt -> g").unwrap().to_string(), "31.1034768 g");
+        assert_eq!(calc("7000 gr -> lb").unwrap().to_string(), "1 lb");
+    }
+
+    #[test]
+    fn area_and_volume() {
+        assert_eq!(calc("1 ha -> ac").unwrap().to_string(), "2.47105381 ac");
+        assert_eq!(calc("1 bbl -> gal").unwrap().to_string(), "42 gal");
+        assert_eq!(calc("2 cup -> floz").unwrap().to_string(), "16 floz");
+        assert_eq!(calc("250 mL + 1 dL").unwrap().to_string(), "350 mL");
+        assert_eq!(calc("1 m^3 -> hL").unwrap().to_string(), "10 hL");
+        // a power of a single unit is not renamed
+        assert_eq!(calc("2 m * 3 m").unwrap().to_string(), "6 m²");
+    }
+
+    #[test]
+    fn energy_power_pressure() {
+        assert_eq!(calc("30 psi -> kPa").unwrap().to_string(), "206.842719 kPa");
+        assert_eq!(calc("1 kWh -> MJ").unwrap().to_string(), "3.6 MJ");
+        assert_eq!(calc("1 kcal -> J").unwrap().to_string(), "4184 J");
+        assert_eq!(calc("1 atm -> Torr").unwrap().to_string(), "760 Torr");
+        assert_eq!(calc("1 hp -> W").unwrap().to_string(), "745.699872 W");
+        assert_eq!(calc("1 kg / (1 m * 1 s^2)").unwrap().to_string(), "1 Pa");
+    }
+
+    #[test]
+    fn time_and_frequency() {
+        assert_eq!(calc("3000 rpm -> Hz").unwrap().to_string(), "50 Hz");
+        assert_eq!(calc("1 yr -> s").unwrap().to_string(), "31557600 s");
+        assert_eq!(calc("1 yr -> d").unwrap().to_string(), "365.25 d");
+        assert_eq!(
+            calc("2 wk -> fortnight").unwrap().to_string(),
+            "1 fortnight"
+        );
+        assert_eq!(calc("1 / 1 ms -> kHz").unwrap().to_string(), "1 kHz");
+    }
+
+    #[test]
+    fn electromagnetic_units() {
+        assert_eq!(calc("3.3 V / 220 Ω -> mA").unwrap().to_string(), "15 mA");
+        assert_eq!(
+            calc("2200 mAh * 3.7 V -> Wh").unwrap().to_string(),
+            "8.14 Wh"
+        );
+        assert_eq!(calc("10 kohm * 1 μF").unwrap().to_string(), "0.01 s");
+        assert_eq!(calc("1 T -> Gs").unwrap().to_string(), "10000 Gs");
+        assert_eq!(
+            calc("1 mol * G").unwrap(),
+            calc("1 mol * gravitational_constant").unwrap()
+        );
+        assert_eq!(calc("1 H * 1 A / 1 s").unwrap().to_string(), "1 V");
+        // a `PS` is still the metric horsepower
+        assert_eq!(calc("1 PS -> W").unwrap().to_string(), "735.49875 W");
+    }
+
+    #[test]
+    fn astronomical_and_atomic_units() {
+        assert_eq!(calc("1 ly / c -> yr").unwrap().to_string(), "1 yr");
+        assert_eq!(calc("1 kpc -> pc").unwrap().to_string(), "1000 pc");
+        assert_eq!(
+            calc("1 M_jup -> M_earth").unwrap().to_string(),
+            "317.827601 M⊕"
+        );
+        // the angstrom sign and the letter Å look alike but are different code points
+        assert_eq!(calc("1 \u{212B} -> nm").unwrap().to_string(), "0.1 nm");
+        assert_eq!(calc("1 \u{C5} -> nm").unwrap().to_string(), "0.1 nm");
+        assert_eq!(calc("12 amu -> Da").unwrap().to_string(), "12 Da");
+        assert_eq!(calc("1 a0 -> Å").unwrap().to_string(), "0.52917721 Å");
+        assert_eq!(calc("2a₀ -> a0").unwrap().to_string(), "2 a₀");
+    }
+
+    #[test]
+    fn velocity_and_acceleration() {
+        assert_eq!(calc("120 kn -> mph").unwrap().to_string(), "138.093534 mph");
+        assert_eq!(calc("100 km/h -> kph").unwrap().to_string(), "100 kph");
+        assert_eq!(
+            calc("0.5 c -> km/s").unwrap().to_string(),
+            "1.498962e5 km/s"
+        );
+        assert_eq!(calc("2 * g0").unwrap().to_string(), "19.6133 m/s²");
+        assert_eq!(calc("3 g0").unwrap().to_string(), "3 g₀");
+        // without a coherent velocity unit, m/s stays as it is
+        assert_eq!(calc("10 m / 1 s").unwrap().to_string(), "10 m/s");
+    }
+
+    #[test]
+    fn formula_units() {
+        assert_eq!(calc("1 lbf -> N").unwrap().to_string(), "4.44822162 N");
+        assert_eq!(calc("1 psi -> Pa").unwrap().to_string(), "6894.75729 Pa");
+        assert_eq!(calc("1 hp -> W").unwrap().to_string(), "745.699872 W");
+        assert_eq!(calc("1 PS -> W").unwrap().to_string(), "735.49875 W");
+        assert_eq!(calc("1 gal -> L").unwrap().to_string(), "3.78541178 L");
+        assert_eq!(calc("2 kWh -> MJ").unwrap().to_string(), "7.2 MJ");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "cannot combine Sv and Gy, which measure equivalent dose and absorbed"
+    )]
+    fn dose_sum() {
+        let _ = calc("1 Sv + 1 Gy");
+    }
+
+    #[test]
+    fn radiation_units() {
+        assert_eq!(calc("1 mCi -> MBq").unwrap().to_string(), "37 MBq");
+        assert_eq!(calc("2 mSv + 3 mSv").unwrap().to_string(), "5 mSv");
+        assert_eq!(calc("10 mSv -> rem").unwrap().to_string(), "1 rem");
+        assert_eq!(calc("100 rd -> Gy").unwrap().to_string(), "1 Gy");
+        assert_eq!(calc("3 μSv").unwrap().to_string(), "3 μSv");
+        // gray and sievert share their dimensions, so neither names a result
+        assert_eq!(calc("1 J / 1 kg").unwrap().to_string(), "1 m²/s²");
+        assert_eq!(
+            calc("2.58e-4 C/kg -> R_exp").unwrap().to_string(),
+            "1 R_exp"
+        );
+        // `R` is the gas constant, also after a unit
+        assert_eq!(
+            calc("1 mol * R").unwrap().to_string(),
+            "8.31446262 m²·kg/s²·K"
+        );
+    }
+
+    #[test]
+    fn unit_systems() {
+        assert_eq!(calc("1 J -> cgs").unwrap().to_string(), "10000000 cm²·g/s²");
+        assert_eq!(calc("1 kWh -> SI").unwrap().to_string(), "3600000 m²·kg/s²");
+        assert_eq!(calc("1500 kg -> mts").unwrap().to_string(), "1.5 t");
+        assert_eq!(calc("20 °C -> imperial").unwrap().to_string(), "68 °F");
+        assert_eq!(calc("10 ΔC -> imperial").unwrap().to_string(), "18 ΔF");
+        assert_eq!(
+            calc("1 eV -> atomic").unwrap().to_string(),
+            "0.03674932 a₀²·mₑ/t_au²"
+        );
+        assert_eq!(
+            calc("hbar -> planck").unwrap().to_string(),
+            "1 l_P²·m_P/t_P"
+        );
+        // results are in the chosen system unless converted
+        let cgs = |input: &str| {
+            let system = crate::core::system::UnitSystem::CGS;
+            let parsers = [parser(), constant_parser()];
+            evaluate(&parsers, &semanter(Some(system)), input)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(cgs("1 J"), "10000000 cm²·g/s²");
+        assert_eq!(cgs("1 J -> kJ"), "0.001 kJ");
+        assert_eq!(cgs("3 dB"), "3 dB");
+    }
+
+    #[test]
+    fn session_units() {
+        registry::define("smoot", &calc("1.7018 m").unwrap()).unwrap();
+        assert_eq!(calc("364.4 smoot").unwrap().to_string(), "364.4 smoot");
+        assert_eq!(
+            calc("1 mi -> smoot").unwrap().to_string(),
+            "945.671642 smoot"
+        );
+        assert_eq!(calc("2smoot/1s").unwrap().to_string(), "3.4036 m/s");
+    }
 }
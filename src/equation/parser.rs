@@ -3,12 +3,22 @@ use earlgrey::{Grammar, GrammarBuilder};
 
 use std::str::FromStr;
 
-use crate::core::UNITS_LOOKUP;
+use crate::core::constant::CONSTANTS_LOOKUP;
+use crate::core::level::LEVELS_LOOKUP;
+use crate::core::registry;
+use crate::core::system::UNIT_SYSTEMS_LOOKUP;
 
+/// Reads a name of both a unit and a constant, such as `h`, as the unit after a number and
+/// after `/` in the units of a quantity, as in `1 km / h`
 pub fn parser() -> EarleyParser {
-    EarleyParser::new(grammar())
+    EarleyParser::new(grammar(true))
 }
-fn grammar() -> Grammar {
+/// Reads such a name as the constant after `/`, as in `1 eV / h -> Hz`, and wherever a unit
+/// cannot be
+pub fn constant_parser() -> EarleyParser {
+    EarleyParser::new(grammar(false))
+}
+fn grammar(units_first: bool) -> Grammar {
     GrammarBuilder::default()
         .nonterm("equation")
         .nonterm("expr")
@@ -20,6 +30,10 @@ fn grammar() -> Grammar {
         .nonterm("func")
         .nonterm("args")
         .nonterm("units")
+        .nonterm("uterm")
+        .nonterm("pterm")
+        .nonterm("target")
+        .nonterm("exponent")
         .nonterm("quantity")
         .terminal("[n]", |n| f64::from_str(n).is_ok())
         .terminal("+", |n| n == "+")
@@ -35,9 +49,22 @@ fn grammar() -> Grammar {
         .terminal("log", |n| n == "log")
         .terminal("sqrt", |n| n == "sqrt")
         .terminal("[->]", |n| n == "->")
-        .terminal("unit", |n| UNITS_LOOKUP.contains_key(n))
+        .terminal("unit", |n| {
+            registry::is_unit(n) && !CONSTANTS_LOOKUP.contains_key(n)
+        })
+        // a unit named like a constant
+        .terminal("shared", |n| {
+            registry::is_unit(n) && CONSTANTS_LOOKUP.contains_key(n)
+        })
+        .terminal("level", |n| LEVELS_LOOKUP.contains_key(n))
+        .terminal("system", |n| UNIT_SYSTEMS_LOOKUP.contains_key(n))
+        .terminal("constant", move |n| {
+            CONSTANTS_LOOKUP.contains_key(n) && !(units_first && registry::is_unit(n))
+        })
         .rule("equation", &["expr"])
-        .rule("equation", &["expr", "[->]", "units"])
+        .rule("equation", &["expr", "[->]", "target"])
+        .rule("equation", &["expr", "[->]", "level"])
+        .rule("equation", &["expr", "[->]", "system"])
         .rule("expr", &["term"])
         .rule("expr", &["expr", "+", "term"])
         .rule("expr", &["expr", "-", "term"])
@@ -49,20 +76,40 @@ fn grammar() -> Grammar {
         .rule("factor", &["-", "factor"])
         .rule("power", &["ufact"])
         .rule("power", &["ufact", "^", "factor"])
+        // units bind tighter than ^, so `1 m / s ^ 2` is an acceleration
+        .rule("power", &["quantity"])
         .rule("ufact", &["group"])
         .rule("ufact", &["ufact", "!"])
-        .rule("group", &["quantity"])
+        .rule("group", &["[n]"])
         .rule("group", &["(", "expr", ")"])
         .rule("group", &["log", "group"])
         .rule("group", &["ln", "group"])
         .rule("group", &["sqrt", "group"])
-        .rule("quantity", &["[n]"])
+        .rule("group", &["constant"])
         .rule("quantity", &["[n]", "units"])
-        .rule("units", &["unit"])
-        .rule("units", &["units", "units"])
-        .rule("units", &["unit", "^", "[n]"])
-        .rule("units", &["units", "*", "units"])
-        .rule("units", &["units", "/", "units"])
+        // logarithmic units stand alone, as `dB/s` has no meaning
+        .rule("quantity", &["[n]", "level"])
+        // after `*` a name of both is the constant, as in `1 mol * F`
+        .rule("units", &["uterm"])
+        .rule("units", &["units", "uterm"])
+        .rule("units", &["units", "*", "pterm"])
+        .rule(
+            "units",
+            &["units", "/", if units_first { "uterm" } else { "pterm" }],
+        )
+        .rule("pterm", &["unit"])
+        .rule("pterm", &["unit", "^", "exponent"])
+        .rule("uterm", &["pterm"])
+        .rule("uterm", &["shared"])
+        .rule("uterm", &["shared", "^", "exponent"])
+        // converted to, where there are no constants
+        .rule("target", &["uterm"])
+        .rule("target", &["target", "uterm"])
+        .rule("target", &["target", "*", "uterm"])
+        .rule("target", &["target", "/", "uterm"])
+        .rule("exponent", &["[n]"])
+        .rule("exponent", &["(", "[n]", ")"])
+        .rule("exponent", &["(", "[n]", "/", "[n]", ")"])
         .into_grammar("equation")
         .expect("Bad Gramar")
 
@@ -148,7 +195,8 @@ mod tests {
 
     #[test]
     fn grammar_ok() {
-        grammar();
+        grammar(true);
+        grammar(false);
     }
 
     #[test]
@@ -211,4 +259,51 @@ mod tests {
         assert!(parse_test("10 ^ 1 s"));
         assert!(parse_test("10 ^ -1 s"));
     }
+
+    #[test]
+    fn fractional_unit_powers() {
+        assert!(parse_test("1 m ^ ( 1 / 3 )"));
+        assert!(parse_test("1 m ^ ( -1 / 2 ) s"));
+        assert!(parse_test("1 m ^ ( 0.5 )"));
+        assert!(parse_test("( 1 m ^ ( 1 / 3 ) ) ^ 3"));
+    }
+
+    #[test]
+    fn unambiguous_units() {
+        for input in [
+            "1 m / s ^ 2",
+            "1 m / s / s",
+            "1 kg m / s ^ 2",
+            "1 m ^ 3 / m / s",
+            "1 km / h",
+            "1 eV / h",
+            "2 h * h",
+            "h * c / 2 m",
+        ] {
+            for parser in [parser(), constant_parser()] {
+                // tokenized, as the tokenizer keeps names like `hbar` whole
+                if let Ok(state) = parser.parse(crate::equation::tokenizer(input.chars())) {
+                    let trees = crate::equation::semanter(None)
+                        .eval_all(&state)
+                        .unwrap()
+                        .len();
+                    assert_eq!(trees, 1, "{} has {} parse trees", input, trees);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn units_and_constants() {
+        // a unit after a number, also after `/` unless the constant parser reads it
+        assert!(parse_test("1 h"));
+        assert!(parse_test("1 km / h"));
+        assert!(parse_test("1 m / s -> km / h"));
+        assert!(!parse_test("2 * h"));
+        assert!(!parse_test("1 mol * F"));
+        let constants = |input: &str| constant_parser().parse(input.split_whitespace()).is_ok();
+        assert!(constants("2 * h"));
+        assert!(constants("1 mol * F"));
+        assert!(constants("1 h * h"));
+    }
 }
@@ -1,32 +1,54 @@
-use crate::core::{
-    quantity::{Quantity, QuantityFloat},
-    unit::UNITS_LOOKUP,
-};
+use crate::core::constant::CONSTANTS_LOOKUP;
+use crate::core::level::LEVELS_LOOKUP;
+use crate::core::registry;
+use crate::core::system::{UnitSystem, UNIT_SYSTEMS_LOOKUP};
+use crate::core::{Quantity, QuantityFloat};
 use log::debug;
 use spfunc::gamma::gamma;
 
 fn symbol_match(symbol: &str, token: &str) -> Quantity {
     let out = match symbol {
         "[n]" => Quantity::from(token.parse::<QuantityFloat>().unwrap()),
-        "e" => Quantity::from(std::f64::consts::E),
-        "pi" => Quantity::from(std::f64::consts::PI),
-        "unit" => *UNITS_LOOKUP.get(token).expect("invalid unit"),
+        "constant" => *CONSTANTS_LOOKUP.get(token).expect("invalid constant"),
+        "unit" | "shared" => registry::unit(token).expect("invalid unit"),
+        "level" => LEVELS_LOOKUP
+            .get(token)
+            .expect("invalid level")
+            .quantity(0.),
+        "system" => Quantity {
+            units: UNIT_SYSTEMS_LOOKUP
+                .get(token)
+                .expect("invalid unit system")
+                .units(),
+            ..1.into()
+        },
         _ => 0.into(),
     };
     debug!("{:?}", out);
     out
 }
 
+// a reading such as 20 °C cannot be multiplied, unlike °C in the units of a quantity
+fn factor(q: Quantity) -> Quantity {
+    q.as_factor().unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn power(unit: Quantity, exponent: Quantity) -> Quantity {
+    unit.pow(exponent.try_into().expect("Quantity is not scalar"))
+        .unwrap_or_else(|e| panic!("{}", e))
+}
+
 macro_rules! debug_action {
     ($ev:ident, $n:ident, $($action:literal, $exp:expr),+ ) => {
-       $( $ev.action($action, |$n| {
+       $( $ev.action($action, move |$n| {
             debug!($action);
             $exp
         }); )+
     };
 }
 
-pub fn semanter<'a>() -> earlgrey::EarleyForest<'a, Quantity> {
+/// Results not converted with `->` are in the base units of `system`, if any
+pub fn semanter<'a>(system: Option<UnitSystem>) -> earlgrey::EarleyForest<'a, Quantity> {
     let mut ev = earlgrey::EarleyForest::new(symbol_match);
     debug_action! {
         ev, n,
@@ -34,8 +56,8 @@ pub fn semanter<'a>() -> earlgrey::EarleyForest<'a, Quantity> {
         "expr -> expr + term",   n[0] + n[2],
         "expr -> expr - term",   n[0] - n[2],
         "term -> factor",        n[0],
-        "term -> term * factor", n[0] * n[2],
-        "term -> term / factor", n[0] / n[2],
+        "term -> term * factor", factor(n[0]) * factor(n[2]),
+        "term -> term / factor", factor(n[0]) / factor(n[2]),
         "term -> term % factor",
                                 (
                                     TryInto::<QuantityFloat>::try_into(n[0]).unwrap()
@@ -47,32 +69,57 @@ pub fn semanter<'a>() -> earlgrey::EarleyForest<'a, Quantity> {
         "factor -> - factor",    n[1].neg(),
         "power -> ufact",        n[0],
         "power -> ufact ^ factor",
-                                 n[0].pow(n[2].try_into().unwrap()),
+                                 factor(n[0]).pow(n[2].try_into().unwrap()).unwrap_or_else(|e| panic!("{}", e)),
 
         "ufact -> group",        n[0],
         "ufact -> ufact !",      gamma::<f64>(TryInto::<QuantityFloat>::try_into(n[0]).unwrap() + 1.).into(),
-        "quantity -> [n]",       n[0],
+        "power -> quantity",     n[0],
         "quantity -> [n] units",
-                                Quantity::new(n[0].value, n[1].dimensions, n[1].units),
-        "units -> unit",        n[0],
-        "units -> units units",  n[0] * n[1],
-        "units -> units * units",
+                                Quantity::new(n[0].value * n[1].value, n[1].dimensions, n[1].units),
+        "quantity -> [n] level",
+                                n[1].units.level.expect("invalid level").quantity(n[0].value),
+        "units -> uterm",        n[0],
+        "units -> units uterm",  n[0] * n[1],
+        "units -> units * pterm",
                                 n[0] * n[2],
-        "units -> units / units",
+        "units -> units / uterm",
                                 n[0] / n[2],
-
-        "units -> unit ^ [n]",
-                                n[0].pow(n[2].try_into().expect("Quantity is not scalar")),
-        "units -> units ^ unit",
-                                n[0].pow(n[2].try_into().expect("Quantity is not scalar")),
-        "group -> quantity",    n[0],
+        "units -> units / pterm",
+                                n[0] / n[2],
+        "pterm -> unit",         n[0],
+        "pterm -> unit ^ exponent",
+                                power(n[0], n[2]),
+        "uterm -> pterm",        n[0],
+        "uterm -> shared",       n[0],
+        "uterm -> shared ^ exponent",
+                                power(n[0], n[2]),
+        "target -> uterm",       n[0],
+        "target -> target uterm",
+                                n[0] * n[1],
+        "target -> target * uterm",
+                                n[0] * n[2],
+        "target -> target / uterm",
+                                n[0] / n[2],
+        "exponent -> [n]",       n[0],
+        "exponent -> ( [n] )",   n[1],
+        "exponent -> ( [n] / [n] )",
+                                n[1] / n[3],
+        "group -> [n]",         n[0],
         "group -> ( expr )",    n[1],
         "group -> sqrt group",   TryInto::<QuantityFloat>::try_into(n[1]).expect("Quantity is not scalar").sqrt().into(),
         "group -> log group",   TryInto::<QuantityFloat>::try_into(n[1]).expect("Quantity is not scalar").log10().into(),
+        "group -> constant",    n[0],
         "group -> ln group",    TryInto::<QuantityFloat>::try_into(n[1]).expect("Quantity is not scalar").ln().into(),
-        "equation -> expr",     n[0],
-        "equation -> expr [->] units",
-                                n[0].set_units(&n[2].units)
+        "equation -> expr",     match system {
+                                    Some(s) if n[0].units.level.is_none() => n[0].in_system(s),
+                                    _ => n[0],
+                                },
+        "equation -> expr [->] target",
+                                n[0].convert(&n[2]).unwrap_or_else(|e| panic!("{}", e)),
+        "equation -> expr [->] level",
+                                n[0].convert(&n[2]).unwrap_or_else(|e| panic!("{}", e)),
+        "equation -> expr [->] system",
+                                n[0].in_system(n[2].units.system.expect("invalid unit system"))
     }
     ev
 }
@@ -85,7 +132,7 @@ mod tests {
     #[inline]
     fn eval(input: &str) -> Quantity {
         debug!("input: {}", input);
-        semanter()
+        semanter(None)
             .eval(&parser().parse(input.split_whitespace()).unwrap())
             .unwrap()
     }
@@ -138,7 +185,7 @@ mod tests {
             Quantity {
                 value: 3.,
                 dimensions: Dimensions {
-                    length: 1.,
+                    length: 1.into(),
                     ..Default::default()
                 },
                 ..Default::default()
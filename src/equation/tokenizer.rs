@@ -1,7 +1,8 @@
 use lexers::Scanner;
 
 trait LarvaeScanner {
-    fn larvae_scan_unit(&mut self) -> Option<String>;
+    fn larvae_scan_number(&mut self) -> Option<String>;
+    fn larvae_scan_unit(&mut self, whole_word: bool) -> Option<String>;
     fn scan_arrow(&mut self) -> Option<String>;
     fn scan_unknown(&mut self) -> Option<String>;
 }
@@ -11,25 +12,75 @@ impl<I: Iterator<Item = char>> Iterator for Tokenizer<I> {
     fn next(&mut self) -> Option<String> {
         self.0.scan_whitespace();
         self.0
-            .scan_number()
+            .larvae_scan_number()
             .or_else(|| self.0.scan_arrow())
             .or_else(|| self.0.scan_math_op())
+            // before identifiers, so that `mΩ` is not split into `m` `Ω`
+            .or_else(|| self.0.larvae_scan_unit(true))
             .or_else(|| self.0.scan_identifier())
-            .or_else(|| self.0.larvae_scan_unit())
+            .or_else(|| self.0.larvae_scan_unit(false))
             .or_else(|| self.0.scan_unknown())
     }
 }
+const DIGITS: &[char] = &['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+
 impl<I: Iterator<Item = char>> LarvaeScanner for Scanner<I> {
-    fn larvae_scan_unit(&mut self) -> Option<String> {
-        for unit in crate::core::unit::UNITS_LOOKUP.keys() {
-            let backtrack = self.buffer_pos();
-            if self.accept_all(unit.chars()) {
-                return Some(unit.to_string());
-            } else {
+    // like `scan_number`, but without imaginary numbers, so that `3indian_rupee` is not read
+    // as `3i` `ndian_rupee`
+    fn larvae_scan_number(&mut self) -> Option<String> {
+        let backtrack = self.buffer_pos();
+        self.accept_any(&['+', '-']);
+        if !self.skip_all(DIGITS) {
+            self.set_buffer_pos(backtrack);
+            return None;
+        }
+        let backtrack = self.buffer_pos();
+        if self.accept(&'.').is_some() && !self.skip_all(DIGITS) {
+            self.set_buffer_pos(backtrack);
+            return Some(self.extract_string());
+        }
+        let backtrack = self.buffer_pos();
+        if self.accept_any(&['e', 'E']).is_some() {
+            self.accept_any(&['+', '-']);
+            if !self.skip_all(DIGITS) {
                 self.set_buffer_pos(backtrack);
             }
         }
-        None
+        Some(self.extract_string())
+    }
+    // with `whole_word`, only units not followed by letters or digits, so that `log` is not `l`
+    fn larvae_scan_unit(&mut self, whole_word: bool) -> Option<String> {
+        // longest match wins so that e.g. `μmol` is not split into `μm` `ol`
+        let backtrack = self.buffer_pos();
+        let mut longest: Option<(&str, isize)> = None;
+        // constants too, for symbols like `ħ` and `μ0`
+        let units = crate::core::unit::UNITS_LOOKUP.keys();
+        let levels = crate::core::level::LEVELS_LOOKUP.keys();
+        let session = crate::core::registry::session_unit_names();
+        for unit in units
+            .chain(session.iter())
+            .chain(levels)
+            .chain(crate::core::constant::CONSTANTS_LOOKUP.keys())
+        {
+            if self.accept_all(unit.chars()) {
+                let end = self.buffer_pos();
+                if longest.is_none_or(|(_, pos)| end > pos) {
+                    longest = Some((unit, end));
+                }
+            }
+            self.set_buffer_pos(backtrack);
+        }
+        let (unit, end) = longest?;
+        self.set_buffer_pos(end);
+        if whole_word
+            && self
+                .peek()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            self.set_buffer_pos(backtrack);
+            return None;
+        }
+        Some(unit.to_string())
     }
     fn scan_arrow(&mut self) -> Option<String> {
         let arrow = "->".to_string();
@@ -116,6 +167,21 @@ mod tests {
         }
     }
     #[test]
+    pub fn test_longest_unit_match() {
+        token_test("3μmol/μm", "3 μmol / μm");
+        token_test("3.3V/220kΩ", "3.3 V / 220 kΩ");
+        token_test("sqrt(1mΩ)", "sqrt ( 1 mΩ )");
+        token_test("2Å*3M☉", "2 Å * 3 M☉");
+    }
+    #[test]
+    pub fn test_constants_and_units() {
+        token_test("1km/h", "1 km / h");
+        token_test("2*h", "2 * h");
+        token_test("h*c -> eV nm", "h * c -> eV nm");
+        token_test("1eV/hbar", "1 eV / hbar");
+        token_test("G*1M_sun", "G * 1 M_sun");
+    }
+    #[test]
     pub fn test_unknown_tokens() {
         token_test("1m&^9", "1 m & ^ 9");
         token_test("1m&$#^9", "1 m & $ # ^ 9");
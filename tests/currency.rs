@@ -0,0 +1,22 @@
+use std::process::Command;
+
+// the rates are read once per process, so each case runs the calculator on its own
+fn larvae(expr: &str, rates: &std::path::Path) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_larvae"))
+        .arg(expr)
+        .env("LARVAE_RATES", rates)
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+#[test]
+fn convert_with_rates_file() {
+    let rates = std::env::temp_dir().join(format!("larvae_rates_{}.toml", std::process::id()));
+    std::fs::write(&rates, "base = \"USD\"\n\n[rates]\nEUR = 0.8\n").unwrap();
+    assert_eq!(larvae("100 EUR -> USD", &rates), "125 $");
+    assert_eq!(larvae("10 USD -> EUR", &rates), "8 €");
+    assert!(larvae("1 GBP -> USD", &rates).contains("no exchange rate for GBP"));
+    std::fs::remove_file(&rates).unwrap();
+    assert!(larvae("100 EUR -> USD", &rates).contains("cannot read exchange rates"));
+}